@@ -0,0 +1,18 @@
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(guard_pages)");
+    #[cfg(feature = "guard-pages")]
+    {
+        // The reservation of a guarded memory needs more than 8 GiB of address space,
+        // elsewhere memories fall back to bounds checked buffers.
+        if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux")
+            && std::env::var("CARGO_CFG_TARGET_POINTER_WIDTH").as_deref() == Ok("64")
+        {
+            println!("cargo:rerun-if-changed=src/vm/guard.c");
+            cc::Build::new()
+                .file("src/vm/guard.c")
+                .flag_if_supported("-fexceptions")
+                .compile("yawguard");
+            println!("cargo:rustc-cfg=guard_pages");
+        }
+    }
+}
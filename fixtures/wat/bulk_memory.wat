@@ -0,0 +1,23 @@
+(module
+  (memory 1)
+  (data $hello "hello")
+  (func (export "init") (param $dst i32)
+    get_local $dst
+    i32.const 0
+    i32.const 5
+    memory.init $hello)
+  (func (export "drop")
+    data.drop $hello)
+  (func (export "copy") (param $dst i32) (param $src i32) (param $len i32)
+    get_local $dst
+    get_local $src
+    get_local $len
+    memory.copy)
+  (func (export "fill") (param $dst i32) (param $value i32) (param $len i32)
+    get_local $dst
+    get_local $value
+    get_local $len
+    memory.fill)
+  (func (export "load8") (param $addr i32) (result i32)
+    get_local $addr
+    i32.load8_u))
@@ -1,8 +1,20 @@
 (module
   (func $i (import "imports" "imported_func") (param i32) (result i32))
+  (export "imported_func" (func $i))
+  (table 1 anyfunc)
+  (elem (i32.const 0) $i)
+  (type $i32_to_i32 (func (param i32) (result i32)))
+  (type $to_i32 (func (result i32)))
   (func (export "exported_func") (result i32)
     i32.const 42
-    call $i))
+    call $i)
+  (func (export "call_imported_indirect") (param $x i32) (result i32)
+    get_local $x
+    i32.const 0
+    call_indirect (type $i32_to_i32))
+  (func (export "call_imported_mistyped") (result i32)
+    i32.const 0
+    call_indirect (type $to_i32)))
 
 ;; const importObject = {
 ;;   imports: {
@@ -10,4 +22,4 @@
 ;;         return 3;
 ;;       }
 ;;   }
-;; };
\ No newline at end of file
+;; };
@@ -0,0 +1,6 @@
+(module
+  (func $infinite_loop
+    (loop $l
+      br $l))
+  (export "infinite_loop" (func $infinite_loop))
+)
@@ -0,0 +1,40 @@
+(module
+  (type $pair (func (param i32) (result i32 i32)))
+  (func $swap (param i32 i32) (result i32 i32)
+    get_local 1
+    get_local 0)
+  (func (export "swap") (param i32 i32) (result i32 i32)
+    get_local 0
+    get_local 1
+    call $swap)
+  (func (export "div_rem") (param $a i32) (param $b i32) (result i32 i32)
+    get_local $a
+    block (type $pair) (param i32) (result i32 i32)
+      get_local $b
+      i32.div_u
+      get_local $a
+      get_local $b
+      i32.rem_u
+      ;; A branch carries both results out of the block.
+      br 0
+    end)
+  (func (export "sum") (param $n i32) (result i32 i32)
+    (local $i i32) (local $sum i32)
+    ;; The loop params carry the counter and the sum of 1..=n.
+    i32.const 0
+    i32.const 0
+    loop (param i32 i32) (result i32 i32)
+      set_local $sum
+      set_local $i
+      get_local $i
+      i32.const 1
+      i32.add
+      tee_local $i
+      get_local $sum
+      get_local $i
+      i32.add
+      get_local $i
+      get_local $n
+      i32.lt_u
+      br_if 0
+    end))
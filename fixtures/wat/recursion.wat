@@ -0,0 +1,8 @@
+(module
+  (func $runaway
+    call $runaway)
+  (func $locals
+    (local i64 i64 i64 i64))
+  (export "runaway" (func $runaway))
+  (export "locals" (func $locals))
+)
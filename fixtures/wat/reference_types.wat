@@ -0,0 +1,30 @@
+(module
+  (type $t (func (result i32)))
+  (table $empty 0 funcref)
+  (table $funcs 2 funcref)
+  (table $refs 0 externref)
+  (elem (table $funcs) (i32.const 0) func $one)
+  (elem declare func $two)
+  (func $one (result i32)
+    i32.const 1)
+  (func $two (result i32)
+    i32.const 2)
+  (func (export "call") (param $i i32) (result i32)
+    get_local $i
+    call_indirect $funcs (type $t))
+  (func (export "set_two") (param $i i32)
+    get_local $i
+    ref.func $two
+    table.set $funcs)
+  (func (export "push") (param $r externref) (result i32)
+    get_local $r
+    i32.const 1
+    table.grow $refs)
+  (func (export "get") (param $i i32) (result externref)
+    get_local $i
+    table.get $refs)
+  (func (export "size") (result i32)
+    table.size $refs)
+  (func (export "is_null") (param $r externref) (result i32)
+    get_local $r
+    ref.is_null))
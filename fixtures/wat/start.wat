@@ -0,0 +1,12 @@
+(module
+  (global $counter (mut i32) (i32.const 0))
+  (func $init
+    get_global $counter
+    i32.const 42
+    i32.add
+    set_global $counter)
+  (func $get (result i32)
+    get_global $counter)
+  (start $init)
+  (export "get" (func $get))
+)
@@ -0,0 +1,16 @@
+(module
+  (func $divide (param $lhs i32) (param $rhs i32) (result i32)
+    get_local $lhs
+    get_local $rhs
+    i32.div_s)
+  (func (export "divide_by_zero") (result i32)
+    i32.const 1
+    i32.const 0
+    call $divide)
+  (memory 1)
+  (func $load (param $addr i32) (result i32)
+    get_local $addr
+    i32.load)
+  (func (export "out_of_bounds") (result i32)
+    i32.const 65535
+    call $load))
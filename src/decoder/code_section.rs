@@ -1,10 +1,11 @@
 use num_traits::*;
-use std::io::{Cursor, Read};
+use std::io::{self, Cursor, Read};
 
 use crate::reader::*;
 use crate::types::*;
 use crate::vm::Opcode;
 
+use super::init_expr::read_ref_type;
 use super::number::*;
 use super::{DecodeError, Decoder};
 
@@ -20,6 +21,8 @@ pub struct LocalEntry {
 pub struct FunctionBody {
     pub locals: Vec<LocalEntry>,
     pub decoded: Vec<Instruction>,
+    /// Offset of each instruction from the start of the code section.
+    pub offsets: Vec<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,139 +35,256 @@ impl Decoder for CodeSection {
     type Error = DecodeError;
 
     fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
+        let mut reader = CountingReader {
+            inner: reader,
+            count: 0,
+        };
+        let reader = &mut reader;
         let count: u32 = VarUint32::decode(reader)?.into();
         let mut bodies: Vec<FunctionBody> = vec![];
         for _ in 0..count {
             let body_size: usize = VarUint32::decode(reader)?.into();
+            let body_offset = reader.count;
             let mut body = Cursor::new(read_bytes(reader, body_size)?);
             let local_count: u32 = VarUint32::decode(&mut body)?.into();
             let mut locals: Vec<LocalEntry> = vec![];
+            let mut total_locals: u64 = 0;
             for _ in 0..local_count {
-                let count = VarUint32::decode(&mut body)?.into();
+                let count: u32 = VarUint32::decode(&mut body)?.into();
+                total_locals += count as u64;
+                if total_locals > u32::MAX as u64 {
+                    return Err(DecodeError::TooManyLocalsError);
+                }
                 let value_type = ValueType::from_u8(read_next(&mut body)?)
                     .ok_or(DecodeError::InvalidValueTypeError)?;
                 locals.push(LocalEntry { count, value_type });
             }
+            let code_offset = body_offset + body.position() as usize;
             let mut code: Vec<u8> = vec![];
             body.read_to_end(&mut code)?;
-            code.pop();
+            if code.pop() != Some(Opcode::End as u8) {
+                return Err(DecodeError::MissingEndError);
+            }
+            let (decoded, offsets) = decode_function_body(&code, code_offset as u32)?;
             bodies.push(FunctionBody {
                 locals,
-                decoded: decode_function_body(&code)?,
+                decoded,
+                offsets,
             })
         }
         Ok(CodeSection { count, bodies })
     }
 }
 
-fn decode_function_body(code: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+/// Counts the bytes read so far, to locate instructions in the code section.
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// Reads the memory index of a bulk memory instruction, which must be zero.
+fn read_zero_flag<R: Read>(reader: &mut R) -> Result<(), DecodeError> {
+    if read_next(reader)? != 0 {
+        return Err(DecodeError::ZeroFlagExpectedError);
+    }
+    Ok(())
+}
+
+/// Reads a block type, which is either a single byte for no or one result,
+/// or a non-negative signed LEB128 index into the type section.
+fn read_block_type<R: Read>(reader: &mut R) -> Result<Operand, DecodeError> {
+    let first = read_next(reader)?;
+    if let Some(t) = ResultType::from_u8(first) {
+        return Ok(Operand::ResultType(t));
+    }
+    let index: i64 = VarInt64::decode(&mut (&[first][..]).chain(reader))?.into();
+    if index < 0 || index > u32::MAX as i64 {
+        return Err(DecodeError::InvalidResultTypeError);
+    }
+    Ok(Operand::TypeIndex(index as u32))
+}
+
+fn decode_function_body(
+    code: &[u8],
+    code_offset: u32,
+) -> Result<(Vec<Instruction>, Vec<u32>), DecodeError> {
     let mut decoded = vec![];
+    let mut offsets = vec![];
     let mut reader = Cursor::new(code);
+    // The final `end` is already stripped, so every block must be closed inside the body.
+    let mut depth = 0usize;
     loop {
         if reader.get_ref().len() == reader.position() as usize {
             break;
         }
-        let mut operands: Vec<Operand> = vec![];
-        let next = read_next(&mut reader)?;
-        let i = Opcode::from_u8(next).ok_or(DecodeError::InvalidOpcodeError)?;
-        match i {
-            Opcode::Block | Opcode::Loop | Opcode::If => {
-                let result_type = ResultType::from_u8(read_next(&mut reader)?)
-                    .ok_or(DecodeError::InvalidResultTypeError)?;
-                operands.push(Operand::ResultType(result_type));
-                decoded.push((i, operands))
-            }
-            Opcode::Br
-            | Opcode::BrIf
-            | Opcode::Call
-            | Opcode::GetLocal
-            | Opcode::SetLocal
-            | Opcode::TeeLocal
-            | Opcode::GetGlobal
-            | Opcode::SetGlobal => {
-                let v: u32 = VarUint32::decode(&mut reader)?.into();
-                operands.push(Operand::U32(v));
-                decoded.push((i, operands))
-            }
-            Opcode::BrTable => {
-                let count: usize = VarUint32::decode(&mut reader)?.into();
-                operands.push(Operand::U32(count as u32));
-                for _ in 0..count {
-                    let v: u32 = VarUint32::decode(&mut reader)?.into();
-                    operands.push(Operand::U32(v));
-                }
-                // Default target
-                let v: u32 = VarUint32::decode(&mut reader)?.into();
+        offsets.push(code_offset + reader.position() as u32);
+        let instruction = decode_instruction(&mut reader)?;
+        match instruction.0 {
+            Opcode::Block | Opcode::Loop | Opcode::If => depth += 1,
+            Opcode::End if depth == 0 => return Err(DecodeError::TrailingCodeError),
+            Opcode::End => depth -= 1,
+            _ => (),
+        }
+        decoded.push(instruction);
+    }
+    if depth != 0 {
+        return Err(DecodeError::MissingEndError);
+    }
+    Ok((decoded, offsets))
+}
+
+/// Reads an instruction with its immediates.
+pub(crate) fn decode_instruction<R: Read>(reader: &mut R) -> Result<Instruction, DecodeError> {
+    let mut operands: Vec<Operand> = vec![];
+    let next = read_next(reader)?;
+    let i = if next == Opcode::MISC_PREFIX {
+        let sub: u32 = VarUint32::decode(reader)?.into();
+        if sub > 0xFF {
+            return Err(DecodeError::InvalidOpcodeError);
+        }
+        Opcode::from_u32((next as u32) << 8 | sub)
+    } else {
+        Opcode::from_u8(next)
+    }
+    .ok_or(DecodeError::InvalidOpcodeError)?;
+    match i {
+        Opcode::Block | Opcode::Loop | Opcode::If => {
+            operands.push(read_block_type(reader)?);
+        }
+        Opcode::Br
+        | Opcode::BrIf
+        | Opcode::Call
+        | Opcode::GetLocal
+        | Opcode::SetLocal
+        | Opcode::TeeLocal
+        | Opcode::GetGlobal
+        | Opcode::SetGlobal
+        | Opcode::TableGet
+        | Opcode::TableSet
+        | Opcode::TableGrow
+        | Opcode::TableSize
+        | Opcode::TableFill
+        | Opcode::RefFunc => {
+            let v: u32 = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(v));
+        }
+        Opcode::BrTable => {
+            let count: usize = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(count as u32));
+            for _ in 0..count {
+                let v: u32 = VarUint32::decode(reader)?.into();
                 operands.push(Operand::U32(v));
-                decoded.push((i, operands))
             }
-            Opcode::CallIndirect => {
-                let v: u32 = VarUint32::decode(&mut reader)?.into();
+            // Default target
+            let v: u32 = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(v));
+        }
+        // [type, table]
+        Opcode::CallIndirect => {
+            for _ in 0..2 {
+                let v: u32 = VarUint32::decode(reader)?.into();
                 operands.push(Operand::U32(v));
-                decoded.push((i, operands));
-                // Reserved
-                let _ = VarUint32::decode(&mut reader)?;
             }
-            Opcode::I32Load
-            | Opcode::I64Load
-            | Opcode::F32Load
-            | Opcode::F64Load
-            | Opcode::I32Load8S
-            | Opcode::I32Load8U
-            | Opcode::I32Load16S
-            | Opcode::I32Load16U
-            | Opcode::I64Load8S
-            | Opcode::I64Load8U
-            | Opcode::I64Load16S
-            | Opcode::I64Load16U
-            | Opcode::I64Load32S
-            | Opcode::I64Load32U
-            | Opcode::I32Store
-            | Opcode::I64Store
-            | Opcode::F32Store
-            | Opcode::F64Store
-            | Opcode::I32Store8
-            | Opcode::I32Store16
-            | Opcode::I64Store8
-            | Opcode::I64Store16
-            | Opcode::I64Store32 => {
-                let flags: u32 = VarUint32::decode(&mut reader)?.into();
-                let offset: u32 = VarUint32::decode(&mut reader)?.into();
-                operands.push(Operand::U32(flags));
-                operands.push(Operand::U32(offset));
-                decoded.push((i, operands))
-            }
-            Opcode::CurrentMemory | Opcode::GrowMemory => {
-                // Reserved
-                let _ = VarUint32::decode(&mut reader)?;
-                decoded.push((i, operands))
-            }
-            Opcode::I32Const => {
-                let v: i32 = VarInt32::decode(&mut reader)?.into();
-                operands.push(Operand::I32(v));
-                decoded.push((i, operands))
-            }
-            Opcode::I64Const => {
-                let v: i64 = VarInt64::decode(&mut reader)?.into();
-                operands.push(Operand::I64(v));
-                decoded.push((i, operands))
+        }
+        Opcode::RefNull => {
+            operands.push(Operand::ValueType(read_ref_type(reader)?));
+        }
+        Opcode::TypedSelect => {
+            let count: u32 = VarUint32::decode(reader)?.into();
+            for _ in 0..count {
+                let value_type = ValueType::from_u8(read_next(reader)?)
+                    .ok_or(DecodeError::InvalidValueTypeError)?;
+                operands.push(Operand::ValueType(value_type));
             }
-            Opcode::F32Const => {
-                let v = read_u32(&mut reader)?;
-                let v = f32::from_bits(v);
-                operands.push(Operand::F32(v));
-                decoded.push((i, operands))
+        }
+        Opcode::I32Load
+        | Opcode::I64Load
+        | Opcode::F32Load
+        | Opcode::F64Load
+        | Opcode::I32Load8S
+        | Opcode::I32Load8U
+        | Opcode::I32Load16S
+        | Opcode::I32Load16U
+        | Opcode::I64Load8S
+        | Opcode::I64Load8U
+        | Opcode::I64Load16S
+        | Opcode::I64Load16U
+        | Opcode::I64Load32S
+        | Opcode::I64Load32U
+        | Opcode::I32Store
+        | Opcode::I64Store
+        | Opcode::F32Store
+        | Opcode::F64Store
+        | Opcode::I32Store8
+        | Opcode::I32Store16
+        | Opcode::I64Store8
+        | Opcode::I64Store16
+        | Opcode::I64Store32 => {
+            let flags: u32 = VarUint32::decode(reader)?.into();
+            // The alignment exponent has to fit in the low five bits.
+            if flags >= 32 {
+                return Err(DecodeError::InvalidMemopFlagsError);
             }
-            Opcode::F64Const => {
-                let v = read_u64(&mut reader)?;
-                let v = f64::from_bits(v);
-                operands.push(Operand::F64(v));
-                decoded.push((i, operands))
+            let offset: u32 = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(flags));
+            operands.push(Operand::U32(offset));
+        }
+        Opcode::MemoryInit => {
+            let v: u32 = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(v));
+            read_zero_flag(reader)?;
+        }
+        Opcode::DataDrop | Opcode::ElemDrop => {
+            let v: u32 = VarUint32::decode(reader)?.into();
+            operands.push(Operand::U32(v));
+        }
+        Opcode::MemoryCopy => {
+            read_zero_flag(reader)?;
+            read_zero_flag(reader)?;
+        }
+        Opcode::MemoryFill => {
+            read_zero_flag(reader)?;
+        }
+        // [segment, table] of `table.init`, [destination, source] of `table.copy`
+        Opcode::TableInit | Opcode::TableCopy => {
+            for _ in 0..2 {
+                let v: u32 = VarUint32::decode(reader)?.into();
+                operands.push(Operand::U32(v));
             }
-            _ => decoded.push((i, operands)),
-        };
+        }
+        Opcode::CurrentMemory | Opcode::GrowMemory => {
+            // Reserved
+            let _ = VarUint32::decode(reader)?;
+        }
+        Opcode::I32Const => {
+            let v: i32 = VarInt32::decode(reader)?.into();
+            operands.push(Operand::I32(v));
+        }
+        Opcode::I64Const => {
+            let v: i64 = VarInt64::decode(reader)?.into();
+            operands.push(Operand::I64(v));
+        }
+        Opcode::F32Const => {
+            let v = read_u32(reader)?;
+            let v = f32::from_bits(v);
+            operands.push(Operand::F32(v));
+        }
+        Opcode::F64Const => {
+            let v = read_u64(reader)?;
+            let v = f64::from_bits(v);
+            operands.push(Operand::F64(v));
+        }
+        _ => {}
     }
-    Ok(decoded)
+    Ok((i, operands))
 }
 
 #[cfg(test)]
@@ -194,11 +314,78 @@ mod tests {
                         (Opcode::I32Add, vec![])
                     ],
                     locals: vec![],
+                    offsets: vec![3, 5, 7],
                 }],
             }
         );
     }
 
+    #[test]
+    fn it_decode_prefixed_opcode() {
+        // (func (param f32) (result i32)
+        //   get_local 0
+        //   i32.trunc_sat_f32_s
+        // )
+        // The sub-opcode is a LEB128 `u32`, so it may be padded.
+        let b = vec![0x01, 0x07, 0x00, 0x20, 0x00, 0xFC, 0x80, 0x00, 0x0B];
+        let mut cur = Cursor::new(b);
+        let section = CodeSection::decode(&mut cur).unwrap();
+        assert_eq!(
+            section.bodies[0].decoded,
+            vec![
+                (Opcode::GetLocal, vec![Operand::U32(0)]),
+                (Opcode::I32TruncSatSF32, vec![])
+            ]
+        );
+        assert_eq!(section.bodies[0].offsets, vec![3, 5]);
+        let b = vec![0x01, 0x04, 0x00, 0xFC, 0x7F, 0x0B];
+        assert!(CodeSection::decode(&mut Cursor::new(b)).is_err());
+    }
+
+    #[test]
+    fn it_decode_bulk_memory_opcodes() {
+        // (func
+        //   memory.init 1 (i32.const 0) (i32.const 0) (i32.const 0)
+        //   data.drop 1
+        //   table.copy (i32.const 0) (i32.const 0) (i32.const 0)
+        // )
+        let b = vec![
+            0x01, 0x19, 0x00, 0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xFC, 0x08, 0x01, 0x00, 0xFC,
+            0x09, 0x01, 0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xFC, 0x0E, 0x00, 0x00, 0x0B,
+        ];
+        let mut cur = Cursor::new(b);
+        let section = CodeSection::decode(&mut cur).unwrap();
+        let decoded = &section.bodies[0].decoded;
+        assert_eq!(decoded[3], (Opcode::MemoryInit, vec![Operand::U32(1)]));
+        assert_eq!(decoded[4], (Opcode::DataDrop, vec![Operand::U32(1)]));
+        assert_eq!(
+            decoded[8],
+            (Opcode::TableCopy, vec![Operand::U32(0), Operand::U32(0)])
+        );
+        // The memory index of `memory.fill` is reserved.
+        let b = vec![0x01, 0x05, 0x00, 0xFC, 0x0B, 0x01, 0x0B];
+        assert!(CodeSection::decode(&mut Cursor::new(b)).is_err());
+    }
+
+    #[test]
+    fn it_decode_block_type_index() {
+        // (func
+        //   block (type 1) end
+        //   loop (type 128) end
+        // )
+        let b = vec![
+            0x01, 0x09, 0x00, 0x02, 0x01, 0x0B, 0x03, 0x80, 0x01, 0x0B, 0x0B,
+        ];
+        let mut cur = Cursor::new(b);
+        let section = CodeSection::decode(&mut cur).unwrap();
+        let decoded = &section.bodies[0].decoded;
+        assert_eq!(decoded[0], (Opcode::Block, vec![Operand::TypeIndex(1)]));
+        assert_eq!(decoded[2], (Opcode::Loop, vec![Operand::TypeIndex(128)]));
+        // Negative indices other than the value types are invalid.
+        let b = vec![0x01, 0x05, 0x00, 0x02, 0x7B, 0x0B, 0x0B];
+        assert!(CodeSection::decode(&mut Cursor::new(b)).is_err());
+    }
+
     #[test]
     fn it_decode_add_local() {
         // (func $add (param $lhs i32) (param $rhs i32) (result i32) (local i32)
@@ -229,6 +416,7 @@ mod tests {
                         count: 1,
                         value_type: ValueType::I32,
                     }],
+                    offsets: vec![5, 8, 10, 12, 14],
                 }],
             }
         );
@@ -293,6 +481,9 @@ mod tests {
                         count: 2,
                         value_type: ValueType::I32,
                     }],
+                    offsets: vec![
+                        5, 7, 9, 11, 13, 14, 16, 18, 20, 21, 23, 25, 27, 28, 30, 32, 33, 34
+                    ],
                 }],
             }
         );
@@ -4,26 +4,29 @@ use std::str::from_utf8;
 use crate::reader::*;
 
 use super::number::*;
-use super::{DecodeError, Decoder};
+use super::{DecodeError, Decoder, SectionKind};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CustomSection {
-	pub name: String,
-	pub payload: Vec<u8>,
+    pub name: String,
+    pub payload: Vec<u8>,
+    /// The known section this one follows, `None` if it precedes all of them.
+    pub after: Option<SectionKind>,
 }
 
 impl Decoder for CustomSection {
-	type Error = DecodeError;
+    type Error = DecodeError;
 
-	fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
-		let name_len: u32 = VarUint32::decode(reader)?.into();
-		let bytes = read_bytes(reader, name_len as usize)?;
-		let name = from_utf8(&bytes)?;
-		let mut payload = vec![];
-		reader.read_to_end(&mut payload)?;
-		Ok(CustomSection {
-			name: name.to_owned(),
-			payload,
-		})
-	}
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
+        let name_len: u32 = VarUint32::decode(reader)?.into();
+        let bytes = read_bytes(reader, name_len as usize)?;
+        let name = from_utf8(&bytes)?;
+        let mut payload = vec![];
+        reader.read_to_end(&mut payload)?;
+        Ok(CustomSection {
+            name: name.to_owned(),
+            payload,
+            after: None,
+        })
+    }
 }
@@ -2,12 +2,11 @@ use std::io::Read;
 
 use super::number::*;
 use super::InitExpr;
-use super::{DecodeError, Decoder};
+use super::{DecodeError, Decoder, SegmentMode};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DataSegment {
-    pub index: u32,
-    pub offset: InitExpr,
+    pub mode: SegmentMode,
     pub data: Vec<u8>,
 }
 
@@ -23,16 +22,26 @@ impl Decoder for DataSection {
         let count: usize = VarUint32::decode(reader)?.into();
         let mut segments: Vec<DataSegment> = vec![];
         for _ in 0..count {
-            let index: u32 = VarUint32::decode(reader)?.into();
-            let offset = InitExpr::new(reader)?;
+            let flags: u32 = VarUint32::decode(reader)?.into();
+            let mode = match flags {
+                0 => SegmentMode::Active {
+                    index: 0,
+                    offset: InitExpr::new(reader)?,
+                },
+                1 => SegmentMode::Passive,
+                2 => {
+                    let index: u32 = VarUint32::decode(reader)?.into();
+                    SegmentMode::Active {
+                        index,
+                        offset: InitExpr::new(reader)?,
+                    }
+                }
+                _ => return Err(DecodeError::InvalidSegmentFlagsError),
+            };
             let size: usize = VarUint32::decode(reader)?.into();
             let mut body = vec![0u8; size];
             reader.read_exact(&mut body)?;
-            segments.push(DataSegment {
-                index,
-                offset,
-                data: body,
-            });
+            segments.push(DataSegment { mode, data: body });
         }
         Ok(DataSection { segments })
     }
@@ -55,11 +64,27 @@ mod tests {
             section,
             DataSection {
                 segments: vec![DataSegment {
-                    offset: InitExpr::RuntimeValue(RuntimeValue::I32(1)),
-                    index: 0x00,
+                    mode: SegmentMode::Active {
+                        index: 0x00,
+                        offset: InitExpr::RuntimeValue(RuntimeValue::I32(1)),
+                    },
                     data: vec![0xA5],
                 }],
             }
         );
     }
+
+    #[test]
+    fn test_passive_data_segment() {
+        let b = vec![0x01, 0x01, 0x02, 0xA5, 0x5A];
+        let mut cur = Cursor::new(b);
+        let section = DataSection::decode(&mut cur).unwrap();
+        assert_eq!(
+            section.segments,
+            vec![DataSegment {
+                mode: SegmentMode::Passive,
+                data: vec![0xA5, 0x5A],
+            }]
+        );
+    }
 }
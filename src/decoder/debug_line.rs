@@ -0,0 +1,534 @@
+use std::fmt;
+use std::io::{self, Cursor, Read};
+use std::str::from_utf8;
+
+use crate::reader::*;
+
+use super::number::*;
+use super::{DecodeError, Decoder};
+
+// Standard opcodes of the line number program.
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+
+// Extended opcodes.
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+const DW_LNE_DEFINE_FILE: u8 = 0x03;
+
+// Content types of the directory and file entries of DWARF 5.
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+// Forms the directory and file entries of DWARF 5 are encoded with.
+const DW_FORM_BLOCK2: u64 = 0x03;
+const DW_FORM_BLOCK4: u64 = 0x04;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_BLOCK1: u64 = 0x0a;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+
+/// A position in a source file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    /// Zero if the column is unknown.
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if self.column != 0 {
+            write!(f, ":{}", self.column)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    address: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+}
+
+/// Rows covering `start..end` contiguously, sorted by address.
+#[derive(Debug, Clone, PartialEq)]
+struct Sequence {
+    start: u64,
+    end: u64,
+    rows: Vec<Row>,
+    /// Index of the unit the sequence belongs to, for its file names.
+    unit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Unit {
+    files: Vec<String>,
+    /// Index of the first file, 1 before DWARF 5.
+    file_base: u64,
+}
+
+/// The line number information of the `.debug_line` custom section, that maps
+/// offsets from the start of the code section back to source lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineTable {
+    units: Vec<Unit>,
+    sequences: Vec<Sequence>,
+}
+
+impl LineTable {
+    /// Decodes the line programs of `.debug_line`. Paths of DWARF 5 may refer to
+    /// the `.debug_line_str` and `.debug_str` sections, empty if the module has none.
+    pub fn decode(
+        debug_line: &[u8],
+        debug_line_str: &[u8],
+        debug_str: &[u8],
+    ) -> Result<LineTable, DecodeError> {
+        let strings = Strings {
+            debug_line_str,
+            debug_str,
+        };
+        let mut table = LineTable::default();
+        let mut reader = Cursor::new(debug_line);
+        while (reader.position() as usize) < debug_line.len() {
+            let (len, offset_size) = match read_u32(&mut reader)? {
+                0xffff_ffff => (read_u64(&mut reader)?, 8),
+                len if len >= 0xffff_fff0 => return Err(DecodeError::InvalidLineProgramError),
+                len => (u64::from(len), 4),
+            };
+            let unit = take(&mut reader, len)?;
+            table.decode_unit(unit, offset_size, &strings)?;
+        }
+        table.sequences.sort_by_key(|s| s.start);
+        Ok(table)
+    }
+
+    fn decode_unit(
+        &mut self,
+        unit: &[u8],
+        offset_size: usize,
+        strings: &Strings,
+    ) -> Result<(), DecodeError> {
+        let mut reader = Cursor::new(unit);
+        let version = read_u16(&mut reader)?;
+        if !(2..=5).contains(&version) {
+            return Err(DecodeError::InvalidLineProgramError);
+        }
+        if version >= 5 {
+            let _address_size = read_next(&mut reader)?;
+            let _segment_selector_size = read_next(&mut reader)?;
+        }
+        let header_len = read_offset(&mut reader, offset_size)?;
+        let program_start = reader
+            .position()
+            .checked_add(header_len)
+            .ok_or(DecodeError::InvalidLineProgramError)?;
+        let min_inst_len = u64::from(read_next(&mut reader)?);
+        if version >= 4 {
+            let _max_ops_per_inst = read_next(&mut reader)?;
+        }
+        let _default_is_stmt = read_next(&mut reader)?;
+        let line_base = read_next(&mut reader)? as i8;
+        let line_range = read_next(&mut reader)?;
+        let opcode_base = read_next(&mut reader)?;
+        if line_range == 0 || opcode_base == 0 {
+            return Err(DecodeError::InvalidLineProgramError);
+        }
+        let opcode_lengths = read_bytes(&mut reader, opcode_base as usize - 1)?;
+
+        let files = if version >= 5 {
+            let dirs = read_entries(&mut reader, offset_size, strings)?
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            read_entries(&mut reader, offset_size, strings)?
+                .into_iter()
+                .map(|(path, dir)| join(&dirs, dir, path))
+                .collect()
+        } else {
+            // The compilation directory is not listed, so directory 0 is left empty.
+            let mut dirs = vec![String::new()];
+            loop {
+                let dir = read_cstr(&mut reader)?;
+                if dir.is_empty() {
+                    break;
+                }
+                dirs.push(dir);
+            }
+            let mut files = vec![];
+            loop {
+                let path = read_cstr(&mut reader)?;
+                if path.is_empty() {
+                    break;
+                }
+                let dir: u64 = VarUint64::decode(&mut reader)?.into();
+                let _mtime = VarUint64::decode(&mut reader)?;
+                let _len = VarUint64::decode(&mut reader)?;
+                files.push(join(&dirs, dir, path));
+            }
+            files
+        };
+        let unit_index = self.units.len();
+        self.units.push(Unit {
+            files,
+            file_base: if version >= 5 { 0 } else { 1 },
+        });
+
+        // Runs the line number program.
+        reader.set_position(program_start);
+        let initial = Row {
+            address: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+        };
+        let mut row = initial.clone();
+        let mut rows = vec![];
+        while (reader.position() as usize) < unit.len() {
+            let opcode = read_next(&mut reader)?;
+            if opcode >= opcode_base {
+                let adjusted = opcode - opcode_base;
+                row.address = row
+                    .address
+                    .wrapping_add(u64::from(adjusted / line_range) * min_inst_len);
+                row.line = row
+                    .line
+                    .wrapping_add((i64::from(line_base) + i64::from(adjusted % line_range)) as u64);
+                rows.push(row.clone());
+                continue;
+            }
+            match opcode {
+                0 => {
+                    let len: u64 = VarUint64::decode(&mut reader)?.into();
+                    let operands = take(&mut reader, len)?;
+                    let (&sub_opcode, operands) = operands
+                        .split_first()
+                        .ok_or(DecodeError::InvalidLineProgramError)?;
+                    match sub_opcode {
+                        DW_LNE_END_SEQUENCE => {
+                            self.push_sequence(rows, row.address, unit_index);
+                            rows = vec![];
+                            row = initial.clone();
+                        }
+                        DW_LNE_SET_ADDRESS => {
+                            row.address =
+                                operands.iter().rev().fold(0, |x, &b| x << 8 | u64::from(b));
+                        }
+                        DW_LNE_DEFINE_FILE => {
+                            let mut operands = Cursor::new(operands);
+                            let path = read_cstr(&mut operands)?;
+                            self.units[unit_index].files.push(path);
+                        }
+                        // Such as `DW_LNE_set_discriminator`.
+                        _ => (),
+                    }
+                }
+                DW_LNS_COPY => rows.push(row.clone()),
+                DW_LNS_ADVANCE_PC => {
+                    let advance: u64 = VarUint64::decode(&mut reader)?.into();
+                    row.address = row.address.wrapping_add(advance.wrapping_mul(min_inst_len));
+                }
+                DW_LNS_ADVANCE_LINE => {
+                    let advance: i64 = VarInt64::decode(&mut reader)?.into();
+                    row.line = row.line.wrapping_add(advance as u64);
+                }
+                DW_LNS_SET_FILE => row.file = VarUint64::decode(&mut reader)?.into(),
+                DW_LNS_SET_COLUMN => row.column = VarUint64::decode(&mut reader)?.into(),
+                DW_LNS_CONST_ADD_PC => {
+                    let advance = u64::from((255 - opcode_base) / line_range) * min_inst_len;
+                    row.address = row.address.wrapping_add(advance);
+                }
+                DW_LNS_FIXED_ADVANCE_PC => {
+                    row.address = row.address.wrapping_add(u64::from(read_u16(&mut reader)?))
+                }
+                // Opcodes that only change flags, or that this reader does not know,
+                // are skipped over their operands.
+                _ => {
+                    for _ in 0..opcode_lengths[opcode as usize - 1] {
+                        VarUint64::decode(&mut reader)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_sequence(&mut self, rows: Vec<Row>, end: u64, unit: usize) {
+        let start = match rows.first() {
+            Some(row) => row.address,
+            None => return,
+        };
+        // The linker moves the code of discarded functions to address 0, which is
+        // never the offset of an instruction.
+        if start == 0 || start >= end {
+            return;
+        }
+        self.sequences.push(Sequence {
+            start,
+            end,
+            rows,
+            unit,
+        });
+    }
+
+    /// Source location of the instruction at `code_offset`, an offset from the
+    /// start of the code section.
+    pub fn find(&self, code_offset: u32) -> Option<SourceLocation> {
+        let address = u64::from(code_offset);
+        let sequence = self
+            .sequences
+            .iter()
+            .find(|s| s.start <= address && address < s.end)?;
+        // The last row at or before the address, the first row being at `start`.
+        let row = &sequence.rows[sequence.rows.partition_point(|r| r.address <= address) - 1];
+        if row.line == 0 {
+            return None;
+        }
+        let unit = &self.units[sequence.unit];
+        let file = row
+            .file
+            .checked_sub(unit.file_base)
+            .and_then(|i| unit.files.get(i as usize))?;
+        Some(SourceLocation {
+            file: file.clone(),
+            line: row.line as u32,
+            column: row.column as u32,
+        })
+    }
+}
+
+struct Strings<'a> {
+    debug_line_str: &'a [u8],
+    debug_str: &'a [u8],
+}
+
+/// Slices the next `len` bytes, failing before allocating if there are not as many.
+fn take<'a>(reader: &mut Cursor<&'a [u8]>, len: u64) -> Result<&'a [u8], DecodeError> {
+    let buf = *reader.get_ref();
+    let start = reader.position() as usize;
+    let end = (start as u64)
+        .checked_add(len)
+        .filter(|&end| end <= buf.len() as u64)
+        .ok_or(DecodeError::InvalidLineProgramError)? as usize;
+    reader.set_position(end as u64);
+    Ok(&buf[start..end])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16, DecodeError> {
+    let b = read_bytes(reader, 2)?;
+    Ok(u16::from(b[0]) | u16::from(b[1]) << 8)
+}
+
+fn read_offset<R: Read>(reader: &mut R, offset_size: usize) -> Result<u64, DecodeError> {
+    Ok(if offset_size == 8 {
+        read_u64(reader)?
+    } else {
+        u64::from(read_u32(reader)?)
+    })
+}
+
+fn read_cstr<R: Read>(reader: &mut R) -> Result<String, DecodeError> {
+    let mut bytes = vec![];
+    loop {
+        match read_next(reader)? {
+            0 => break,
+            b => bytes.push(b),
+        }
+    }
+    Ok(from_utf8(&bytes)?.to_owned())
+}
+
+fn str_at(section: &[u8], offset: u64) -> Result<String, DecodeError> {
+    let bytes = section
+        .get(offset as usize..)
+        .ok_or(DecodeError::InvalidLineProgramError)?;
+    read_cstr(&mut &bytes[..])
+}
+
+/// Reads the directory or file entries of DWARF 5 as pairs of path and directory index.
+fn read_entries<R: Read>(
+    reader: &mut R,
+    offset_size: usize,
+    strings: &Strings,
+) -> Result<Vec<(String, u64)>, DecodeError> {
+    let format_count = read_next(reader)?;
+    let mut format = vec![];
+    for _ in 0..format_count {
+        let content_type: u64 = VarUint64::decode(reader)?.into();
+        let form: u64 = VarUint64::decode(reader)?.into();
+        format.push((content_type, form));
+    }
+    let count: u64 = VarUint64::decode(reader)?.into();
+    let mut entries = vec![];
+    for _ in 0..count {
+        let mut path = String::new();
+        let mut dir = 0;
+        for &(content_type, form) in &format {
+            let mut number = 0;
+            match form {
+                DW_FORM_STRING => path = read_cstr(reader)?,
+                DW_FORM_LINE_STRP => {
+                    path = str_at(strings.debug_line_str, read_offset(reader, offset_size)?)?
+                }
+                DW_FORM_STRP => {
+                    path = str_at(strings.debug_str, read_offset(reader, offset_size)?)?
+                }
+                DW_FORM_UDATA => number = VarUint64::decode(reader)?.into(),
+                DW_FORM_DATA1 => number = u64::from(read_next(reader)?),
+                DW_FORM_DATA2 => number = u64::from(read_u16(reader)?),
+                DW_FORM_DATA4 => number = u64::from(read_u32(reader)?),
+                DW_FORM_DATA8 => number = read_u64(reader)?,
+                DW_FORM_DATA16 => {
+                    read_bytes(reader, 16)?;
+                }
+                DW_FORM_BLOCK | DW_FORM_BLOCK1 | DW_FORM_BLOCK2 | DW_FORM_BLOCK4 => {
+                    let len = match form {
+                        DW_FORM_BLOCK1 => u64::from(read_next(reader)?),
+                        DW_FORM_BLOCK2 => u64::from(read_u16(reader)?),
+                        DW_FORM_BLOCK4 => u64::from(read_u32(reader)?),
+                        _ => VarUint64::decode(reader)?.into(),
+                    };
+                    if io::copy(&mut reader.take(len), &mut io::sink())? != len {
+                        return Err(DecodeError::InvalidLineProgramError);
+                    }
+                }
+                _ => return Err(DecodeError::InvalidLineProgramError),
+            }
+            if content_type == DW_LNCT_DIRECTORY_INDEX {
+                dir = number;
+            } else if content_type == DW_LNCT_PATH && path.is_empty() {
+                return Err(DecodeError::InvalidLineProgramError);
+            }
+        }
+        entries.push((path, dir));
+    }
+    Ok(entries)
+}
+
+/// Prefixes a relative `path` with its directory.
+fn join(dirs: &[String], dir: u64, path: String) -> String {
+    match dirs.get(dir as usize) {
+        // Directory 0 is the compilation directory, and paths relative to it
+        // are the ones a developer knows, such as `src/lib.rs`.
+        Some(dir_path) if dir != 0 && !dir_path.is_empty() && !path.starts_with('/') => {
+            format!("{}/{}", dir_path.trim_end_matches('/'), path)
+        }
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn reject_overflowing_header_length() {
+        #[rustfmt::skip]
+        let section = vec![
+            0xff, 0xff, 0xff, 0xff, // 64-bit DWARF
+            0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // unit length
+            0x04, 0x00, // version
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // header length
+        ];
+        assert!(matches!(
+            LineTable::decode(&section, &[], &[]),
+            Err(DecodeError::InvalidLineProgramError)
+        ));
+    }
+
+    #[test]
+    fn decode_line_program_v4() {
+        #[rustfmt::skip]
+        let program = vec![
+            0x04, 0x00, // version
+            0x22, 0x00, 0x00, 0x00, // header length
+            0x01, 0x01, 0x01, // min inst length, max ops per inst, default is stmt
+            0xfb, 0x0e, 0x0d, // line base -5, line range 14, opcode base 13
+            0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01,
+            0x73, 0x72, 0x63, 0x00, 0x00, // include directory "src"
+            0x6c, 0x69, 0x62, 0x2e, 0x72, 0x73, 0x00, 0x01, 0x00, 0x00, // "lib.rs" in "src"
+            0x00,
+            0x00, 0x05, 0x02, 0x20, 0x00, 0x00, 0x00, // set address 0x20
+            0x03, 0x29, // advance line by 41 to 42
+            0x05, 0x05, // set column 5
+            0x01, // copy
+            0x13, // special opcode: advance address by 0 and line by 1
+            0x2f, // special opcode: advance address by 2 and line by 1
+            0x02, 0x04, // advance pc by 4
+            0x00, 0x01, 0x01, // end sequence at 0x26
+        ];
+        let mut section = vec![program.len() as u8, 0x00, 0x00, 0x00];
+        section.extend(program);
+        let table = LineTable::decode(&section, &[], &[]).unwrap();
+        let location = |line, column| {
+            Some(SourceLocation {
+                file: "src/lib.rs".to_owned(),
+                line,
+                column,
+            })
+        };
+        assert_eq!(table.find(0x1f), None);
+        assert_eq!(table.find(0x20), location(43, 5));
+        assert_eq!(table.find(0x21), location(43, 5));
+        assert_eq!(table.find(0x22), location(44, 5));
+        assert_eq!(table.find(0x25), location(44, 5));
+        assert_eq!(table.find(0x26), None);
+        assert_eq!(location(44, 5).unwrap().to_string(), "src/lib.rs:44:5");
+    }
+
+    #[test]
+    fn decode_line_program_v5() {
+        #[rustfmt::skip]
+        let program = vec![
+            0x05, 0x00, // version
+            0x04, 0x00, // address size, segment selector size
+            0x2c, 0x00, 0x00, 0x00, // header length
+            0x01, 0x01, 0x01, 0xfb, 0x0e, 0x0d,
+            0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01,
+            0x01, 0x01, 0x1f, // directories are paths in .debug_line_str
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, // "/work", "src"
+            0x02, 0x01, 0x08, 0x02, 0x0b, // files are strings with a directory index
+            0x01, 0x6d, 0x61, 0x69, 0x6e, 0x2e, 0x63, 0x00, 0x01, // "main.c" in "src"
+            0x00, 0x05, 0x02, 0x10, 0x00, 0x00, 0x00, // set address 0x10
+            0x01, // copy
+            0x00, 0x01, 0x01, // end sequence at 0x10
+            0x00, 0x05, 0x02, 0x30, 0x00, 0x00, 0x00, // set address 0x30
+            0x04, 0x00, // set file 0
+            0x14, // special opcode: advance line by 2
+            0x21, // special opcode: advance address by 1
+            0x00, 0x01, 0x01, // end sequence at 0x31
+        ];
+        let mut section = vec![program.len() as u8, 0x00, 0x00, 0x00];
+        section.extend(program);
+        let line_str = b"/work\0src\0";
+        let table = LineTable::decode(&section, line_str, &[]).unwrap();
+        assert_eq!(table.find(0x10), None);
+        assert_eq!(
+            table.find(0x30),
+            Some(SourceLocation {
+                file: "src/main.c".to_owned(),
+                line: 3,
+                column: 0,
+            })
+        );
+        assert_eq!(table.find(0x30).unwrap().to_string(), "src/main.c:3");
+        assert_eq!(table.find(0x31), None);
+    }
+}
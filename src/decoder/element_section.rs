@@ -1,14 +1,20 @@
+use num_traits::FromPrimitive;
 use std::io::Read;
 
+use crate::reader::*;
+use crate::types::{ElemType, RuntimeValue};
+use crate::vm::{Functions, Globals};
+
 use super::number::*;
 use super::InitExpr;
-use super::{DecodeError, Decoder};
+use super::{DecodeError, Decoder, SegmentMode};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ElementSegment {
-    pub table_index: u32,
-    pub offset: InitExpr,
-    pub elems: Vec<u32>,
+    pub mode: SegmentMode,
+    pub elem_type: ElemType,
+    /// Function indices are given as `InitExpr::FuncRef`.
+    pub elems: Vec<InitExpr>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,23 +22,73 @@ pub struct ElementSection {
     pub entries: Vec<ElementSegment>,
 }
 
+impl ElementSegment {
+    /// Evaluates the references of the segment.
+    pub(crate) fn eval_elems(
+        &self,
+        globals: &Globals,
+        functions: &Functions,
+    ) -> Result<Vec<RuntimeValue>, DecodeError> {
+        self.elems
+            .iter()
+            .map(|e| e.eval(globals, functions))
+            .collect()
+    }
+}
+
 impl Decoder for ElementSection {
     type Error = DecodeError;
 
+    /// Decodes the segments of the bulk memory encoding, where bit 0 of the flags
+    /// tells a passive or declarative segment, bit 1 an explicit table index or a
+    /// declarative segment, and bit 2 elements given as expressions.
     fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
         let count: u32 = VarUint32::decode(reader)?.into();
         let mut entries: Vec<ElementSegment> = vec![];
         for _ in 0..count {
-            let table_index: u32 = VarUint32::decode(reader)?.into();
-            let offset = InitExpr::new(reader)?;
+            let flags: u32 = VarUint32::decode(reader)?.into();
+            if flags > 7 {
+                return Err(DecodeError::InvalidSegmentFlagsError);
+            }
+            let mode = match flags & 0b011 {
+                0b000 => SegmentMode::Active {
+                    index: 0,
+                    offset: InitExpr::new(reader)?,
+                },
+                0b010 => {
+                    let index: u32 = VarUint32::decode(reader)?.into();
+                    SegmentMode::Active {
+                        index,
+                        offset: InitExpr::new(reader)?,
+                    }
+                }
+                0b001 => SegmentMode::Passive,
+                _ => SegmentMode::Declarative,
+            };
+            let uses_exprs = flags & 0b100 != 0;
+            // Only segments with neither flag leave the element type implicit.
+            let elem_type = match flags & 0b011 {
+                0b000 => ElemType::AnyFunc,
+                // `elemkind`, where 0x00 is `funcref`.
+                _ if !uses_exprs => match read_next(reader)? {
+                    0x00 => ElemType::AnyFunc,
+                    _ => return Err(DecodeError::InvalidElementTypeError),
+                },
+                _ => ElemType::from_u8(read_next(reader)?)
+                    .ok_or(DecodeError::InvalidElementTypeError)?,
+            };
             let num: u32 = VarUint32::decode(reader)?.into();
-            let mut elems: Vec<u32> = vec![];
+            let mut elems = vec![];
             for _ in 0..num {
-                elems.push(VarUint32::decode(reader)?.into())
+                if uses_exprs {
+                    elems.push(InitExpr::new(reader)?);
+                } else {
+                    elems.push(InitExpr::FuncRef(VarUint32::decode(reader)?.into()));
+                }
             }
             entries.push(ElementSegment {
-                table_index,
-                offset,
+                mode,
+                elem_type,
                 elems,
             })
         }
@@ -44,7 +100,6 @@ impl Decoder for ElementSection {
 mod tests {
 
     use super::*;
-    use crate::types::RuntimeValue;
     use std::io::Cursor;
 
     #[test]
@@ -57,11 +112,46 @@ mod tests {
             section,
             ElementSection {
                 entries: vec![ElementSegment {
-                    table_index: 0,
-                    offset: InitExpr::RuntimeValue(RuntimeValue::I32(0)),
-                    elems: vec![0x00, 0x01]
+                    mode: SegmentMode::Active {
+                        index: 0,
+                        offset: InitExpr::RuntimeValue(RuntimeValue::I32(0)),
+                    },
+                    elem_type: ElemType::AnyFunc,
+                    elems: vec![InitExpr::FuncRef(0x00), InitExpr::FuncRef(0x01)]
                 }]
             }
         );
     }
+
+    #[test]
+    fn test_passive_and_declarative_elem_segments() {
+        // (elem func $f1)
+        // (elem funcref (ref.func $f0) (ref.null func))
+        // (elem declare func $f2)
+        let b = vec![
+            0x03, 0x01, 0x00, 0x01, 0x01, 0x05, 0x70, 0x02, 0xD2, 0x00, 0x0B, 0xD0, 0x70, 0x0B,
+            0x03, 0x00, 0x01, 0x02,
+        ];
+        let mut cur = Cursor::new(b);
+        let section = ElementSection::decode(&mut cur).unwrap();
+        let segment = |mode, elems| ElementSegment {
+            mode,
+            elem_type: ElemType::AnyFunc,
+            elems,
+        };
+        assert_eq!(
+            section.entries,
+            vec![
+                segment(SegmentMode::Passive, vec![InitExpr::FuncRef(1)]),
+                segment(
+                    SegmentMode::Passive,
+                    vec![
+                        InitExpr::FuncRef(0),
+                        InitExpr::RuntimeValue(RuntimeValue::FuncRef(None))
+                    ]
+                ),
+                segment(SegmentMode::Declarative, vec![InitExpr::FuncRef(2)]),
+            ]
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use num_traits::*;
 use std::io::Read;
+use std::str::from_utf8;
 
 use crate::reader::*;
 
@@ -29,9 +30,9 @@ impl Decoder for ExportSection {
         for _ in 0..count {
             let field_len = VarUint32::decode(reader)?.into();
             let name = read_bytes(reader, field_len)?;
-            let name = String::from_utf8(name).expect("should convert name to utf8");
-            let kind = read_next(reader)?;
-            let kind = ExternalKind::from_u8(kind).expect("should convert u8 to external kind");
+            let name = from_utf8(&name)?.to_owned();
+            let kind = ExternalKind::from_u8(read_next(reader)?)
+                .ok_or(DecodeError::InvalidExternalKindError)?;
             let index: u32 = VarUint32::decode(reader)?.into();
             entries.push(ExportEntry { name, kind, index });
         }
@@ -4,7 +4,7 @@ use std::io::Read;
 use super::number::*;
 use super::{DecodeError, Decoder};
 
-use super::read_initial_value;
+use super::InitExpr;
 use crate::reader::*;
 use crate::types::*;
 
@@ -17,14 +17,18 @@ pub struct GlobalType {
 #[derive(Debug, Clone, PartialEq)]
 pub struct GlobalVariable {
     pub global_type: GlobalType,
-    pub initial_value: RuntimeValue,
+    pub initial_value: InitExpr,
 }
 
 impl GlobalType {
     pub(crate) fn new<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
-        let content_type = read_next(reader)?;
-        let content_type = ValueType::from_u8(content_type).unwrap();
-        let mutability = read_next(reader)? != 0;
+        let content_type =
+            ValueType::from_u8(read_next(reader)?).ok_or(DecodeError::InvalidValueTypeError)?;
+        let mutability = match read_next(reader)? {
+            0 => false,
+            1 => true,
+            _ => return Err(DecodeError::InvalidMutabilityError),
+        };
         Ok(GlobalType {
             mutability,
             value_type: content_type,
@@ -45,8 +49,7 @@ impl Decoder for GlobalSection {
         let mut globals: Vec<GlobalVariable> = vec![];
         for _ in 0..count {
             let global_type = GlobalType::new(reader)?;
-            // TODO: fix to use initializer
-            let initial_value = read_initial_value(reader)?;
+            let initial_value = InitExpr::new(reader)?;
             globals.push(GlobalVariable {
                 global_type,
                 initial_value,
@@ -59,7 +62,10 @@ impl Decoder for GlobalSection {
 #[cfg(test)]
 mod tests {
 
-    use super::{Decoder, GlobalSection, GlobalType, GlobalVariable, RuntimeValue, ValueType};
+    use super::{Decoder, GlobalSection, GlobalType, GlobalVariable, InitExpr};
+    use super::{RuntimeValue, ValueType};
+    use crate::types::Operand;
+    use crate::vm::Opcode;
     use std::io::Cursor;
 
     #[test]
@@ -76,7 +82,7 @@ mod tests {
                         mutability: false,
                         value_type: ValueType::I32,
                     },
-                    initial_value: RuntimeValue::I32(1),
+                    initial_value: InitExpr::RuntimeValue(RuntimeValue::I32(1)),
                 }],
             }
         );
@@ -96,9 +102,25 @@ mod tests {
                         mutability: true,
                         value_type: ValueType::F32,
                     },
-                    initial_value: RuntimeValue::F32(1.11),
+                    initial_value: InitExpr::RuntimeValue(RuntimeValue::F32(1.11)),
                 }],
             }
         );
     }
+
+    #[test]
+    fn test_global_section_with_non_constant_expr() {
+        // (global i32 (i32.ctz (i32.const 0)))
+        let b = vec![0x01, 0x7F, 0x00, 0x41, 0x00, 0x68, 0x0B];
+        let mut cur = Cursor::new(b);
+        let section = GlobalSection::decode(&mut cur).unwrap();
+
+        assert_eq!(
+            section.globals[0].initial_value,
+            InitExpr::Expr(vec![
+                (Opcode::I32Const, vec![Operand::I32(0)]),
+                (Opcode::I32Ctz, vec![]),
+            ])
+        );
+    }
 }
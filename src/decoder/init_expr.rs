@@ -1,62 +1,104 @@
 use num_traits::*;
 use std::io::Read;
 
-use super::number::*;
-use super::{DecodeError, Decoder};
+use super::code_section::{decode_instruction, Instruction};
+use super::DecodeError;
 
 use crate::reader::*;
 use crate::types::*;
 use crate::vm::instructions::Opcode;
-use crate::vm::Globals;
+use crate::vm::{Functions, Globals};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum InitExpr {
     RuntimeValue(RuntimeValue),
     GlobalIndex(usize),
+    /// `ref.func`, with the index of the function.
+    FuncRef(u32),
+    /// Any other expression, which is not constant or has a wrong number of
+    /// values and is rejected by the validator.
+    Expr(Vec<Instruction>),
 }
 
 impl InitExpr {
     pub(crate) fn new<R: Read>(reader: &mut R) -> Result<InitExpr, DecodeError> {
-        let opcode = Opcode::from_u8(read_next(reader)?).unwrap();
-        let v = match opcode {
-            Opcode::I32Const => {
-                let v: RuntimeValue = VarInt32::decode(reader)?.into();
-                InitExpr::RuntimeValue(v)
+        let mut instructions = vec![];
+        let mut depth = 0;
+        loop {
+            let (opcode, operands) = decode_instruction(reader)?;
+            match opcode {
+                Opcode::Block | Opcode::Loop | Opcode::If => depth += 1,
+                Opcode::End if depth == 0 => break,
+                Opcode::End => depth -= 1,
+                _ => {}
             }
-            Opcode::I64Const => {
-                let v: RuntimeValue = VarInt64::decode(reader)?.into();
-                InitExpr::RuntimeValue(v)
-            }
-            Opcode::F32Const => {
-                let v = read_u32(reader)?;
-                let v = f32::from_bits(v);
-                InitExpr::RuntimeValue(RuntimeValue::F32(v))
-            }
-            Opcode::F64Const => {
-                let v = read_u64(reader)?;
-                let v = f64::from_bits(v);
-                InitExpr::RuntimeValue(RuntimeValue::F64(v))
-            }
-            Opcode::GetGlobal => {
-                let index: usize = VarUint32::decode(reader)?.into();
-                InitExpr::GlobalIndex(index)
-            }
-            _ => return Err(DecodeError::InvalidInitializerError),
+            instructions.push((opcode, operands));
+        }
+        let v = match instructions.as_slice() {
+            [(opcode, operands)] => match opcode {
+                Opcode::I32Const | Opcode::I64Const | Opcode::F32Const | Opcode::F64Const => {
+                    InitExpr::RuntimeValue(operands[0].into())
+                }
+                Opcode::GetGlobal => InitExpr::GlobalIndex(operands[0].into()),
+                Opcode::RefNull => match operands[0] {
+                    Operand::ValueType(t) => InitExpr::RuntimeValue(t.into()),
+                    _ => return Err(DecodeError::InvalidInitializerError),
+                },
+                Opcode::RefFunc => InitExpr::FuncRef(operands[0].into()),
+                _ => InitExpr::Expr(instructions),
+            },
+            _ => InitExpr::Expr(instructions),
         };
-        // Read 0x0B
-        let _ = read_next(reader)?;
         Ok(v)
     }
 
-    pub(crate) fn eval(&self, globals: &Globals) -> Result<RuntimeValue, DecodeError> {
+    /// The function that a `ref.func` refers to.
+    pub(crate) fn func_index(&self) -> Option<u32> {
+        match self {
+            Self::FuncRef(index) => Some(*index),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn eval(
+        &self,
+        globals: &Globals,
+        functions: &Functions,
+    ) -> Result<RuntimeValue, DecodeError> {
         match self {
-            Self::RuntimeValue(v) => Ok(*v),
+            Self::RuntimeValue(v) => Ok(v.clone()),
             Self::GlobalIndex(index) => {
                 if let Some(v) = globals.get(*index) {
-                    return Ok(v.borrow().value);
+                    return Ok(v.borrow().value.clone());
                 }
                 Err(DecodeError::InvalidInitializerError)
             }
+            Self::FuncRef(index) => {
+                let func = functions
+                    .get_ref(*index as usize)
+                    .map_err(|_| DecodeError::InvalidInitializerError)?;
+                Ok(RuntimeValue::FuncRef(Some(FuncRef(func))))
+            }
+            Self::Expr(_) => Err(DecodeError::InvalidInitializerError),
+        }
+    }
+
+    /// Evaluates the offset of an active segment, which the validator typed as `i32`.
+    pub(crate) fn eval_offset(
+        &self,
+        globals: &Globals,
+        functions: &Functions,
+    ) -> Result<u32, DecodeError> {
+        match self.eval(globals, functions)? {
+            RuntimeValue::I32(v) => Ok(v as u32),
+            _ => Err(DecodeError::InvalidInitializerError),
         }
     }
 }
+
+/// Reads the type of `ref.null`, which must be a reference type.
+pub(crate) fn read_ref_type<R: Read>(reader: &mut R) -> Result<ValueType, DecodeError> {
+    ValueType::from_u8(read_next(reader)?)
+        .filter(|t| t.is_ref())
+        .ok_or(DecodeError::InvalidValueTypeError)
+}
@@ -34,10 +34,8 @@ impl Decoder for MemorySection {
     type Error = DecodeError;
 
     fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
-        let count: u8 = VarUint7::decode(reader)?.into();
-        if count > 1 {
-            return Err(DecodeError::InvalidMemoryCountError);
-        }
+        // More than one memory is rejected by the validator.
+        let count: u32 = VarUint32::decode(reader)?.into();
         let mut entries = vec![];
         for _ in 0..count {
             let memory_type = MemoryType::new(reader)?;
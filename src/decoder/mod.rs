@@ -4,13 +4,14 @@ pub mod number;
 mod code_section;
 mod custom_section;
 mod data_section;
+mod debug_line;
 mod element_section;
 mod function_section;
 mod global_section;
 mod import_section;
 mod init_expr;
-mod initial_value;
 mod memory_section;
+mod name_section;
 mod start_section;
 mod table_section;
 mod type_section;
@@ -19,6 +20,7 @@ mod types;
 pub use code_section::{CodeSection, Instruction, LocalEntry};
 pub use custom_section::CustomSection;
 pub use data_section::DataSection;
+pub use debug_line::{LineTable, SourceLocation};
 pub use element_section::ElementSection;
 pub use export_section::ExportSection;
 pub use function_section::FunctionSection;
@@ -26,34 +28,73 @@ pub use global_section::{GlobalSection, GlobalType};
 pub use import_section::{ImportSection, ImportType};
 pub use init_expr::InitExpr;
 pub use memory_section::{MemorySection, MemoryType};
+pub use name_section::{IndirectNameMap, NameMap, NameSection};
 pub use number::*;
 pub use start_section::StartSection;
 pub use table_section::{TableSection, TableType};
 pub use type_section::{FuncType, TypeSection};
 pub use types::*;
 
-use initial_value::*;
 use num_derive::*;
 use std::io::{Cursor, Error, Read};
 use std::str::Utf8Error;
 
+use crate::reader::read_bytes;
+use crate::vm::Opcode;
+
 #[derive(Debug, Fail)]
 pub enum DecodeError {
-    #[fail(display = "Invalid VarUint32 format Error")]
-    InvalidVarUint32Error,
+    #[fail(display = "integer representation too long")]
+    IntegerRepresentationTooLongError,
 
-    #[fail(display = "Invalid VarUint64 format Error")]
-    InvalidVarUint64Error,
+    #[fail(display = "integer too large")]
+    IntegerTooLargeError,
 
-    #[fail(display = "Invalid VarInt32 format Error")]
-    InvalidVarInt32Error,
+    #[fail(display = "magic header not detected")]
+    InvalidMagicNumberError,
 
-    #[fail(display = "Invalid VarInt64 format Error")]
-    InvalidVarInt64Error,
+    #[fail(display = "unknown binary version")]
+    UnknownVersionError,
 
     #[fail(display = "Invalid Section Kind format Error")]
     InvalidSectionKindFormatError,
 
+    #[fail(display = "section out of order")]
+    InvalidSectionOrderError,
+
+    #[fail(display = "duplicate section")]
+    DuplicateSectionError,
+
+    #[fail(display = "section size mismatch")]
+    SectionSizeMismatchError,
+
+    #[fail(display = "function and code section have inconsistent lengths")]
+    FunctionCodeCountMismatchError,
+
+    #[fail(display = "data count and data section have inconsistent lengths")]
+    DataCountMismatchError,
+
+    #[fail(display = "data count section required")]
+    DataCountRequiredError,
+
+    #[fail(display = "malformed segment flags")]
+    InvalidSegmentFlagsError,
+
+    #[fail(display = "malformed memop flags")]
+    InvalidMemopFlagsError,
+
+    #[fail(display = "zero flag expected")]
+    ZeroFlagExpectedError,
+
+    #[fail(display = "END opcode expected")]
+    MissingEndError,
+
+    #[fail(display = "operators remaining after end of function")]
+    TrailingCodeError,
+
+    #[fail(display = "too many locals")]
+    TooManyLocalsError,
+
     #[fail(display = "Invalid Type Section format Error")]
     InvalidTypeSectionError,
 
@@ -66,12 +107,6 @@ pub enum DecodeError {
     #[fail(display = "Invalid element Type Error")]
     InvalidElementTypeError,
 
-    #[fail(display = "Invalid table count Error: table count must one or less in MVP")]
-    InvalidTableCountError,
-
-    #[fail(display = "Invalid memory count Error: memory count must one or less in MVP")]
-    InvalidMemoryCountError,
-
     #[fail(display = "invalid value type error")]
     InvalidValueTypeError,
 
@@ -81,6 +116,15 @@ pub enum DecodeError {
     #[fail(display = "invalid opcode error")]
     InvalidOpcodeError,
 
+    #[fail(display = "invalid external kind error")]
+    InvalidExternalKindError,
+
+    #[fail(display = "invalid mutability error")]
+    InvalidMutabilityError,
+
+    #[fail(display = "invalid DWARF line number program")]
+    InvalidLineProgramError,
+
     #[fail(display = "Some I/O Error: {:?}", error)]
     IOError { error: Error },
 
@@ -105,7 +149,7 @@ pub trait Decoder: Sized {
     fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error>;
 }
 
-#[derive(Debug, Clone, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum SectionKind {
     Custom = 0x00,
     Type = 0x01,
@@ -119,11 +163,25 @@ pub enum SectionKind {
     Element = 0x09,
     Code = 0x0A,
     Data = 0x0B,
+    DataCount = 0x0C,
+}
+
+impl SectionKind {
+    /// Position of the section in a module. The data count section comes before
+    /// the code section despite its id, so that function bodies can be checked in one pass.
+    fn order(self) -> u8 {
+        match self {
+            SectionKind::DataCount => SectionKind::Code as u8,
+            SectionKind::Code | SectionKind::Data => self as u8 + 1,
+            _ => self as u8,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Sections {
-    pub(crate) custom_section: Option<CustomSection>,
+    /// Every custom section, in the order they appear.
+    pub(crate) custom_sections: Vec<CustomSection>,
     pub(crate) type_section: Option<TypeSection>,
     pub(crate) import_section: Option<ImportSection>,
     pub(crate) function_section: Option<FunctionSection>,
@@ -135,12 +193,25 @@ pub struct Sections {
     pub(crate) code_section: Option<CodeSection>,
     pub(crate) data_section: Option<DataSection>,
     pub(crate) element_section: Option<ElementSection>,
+    /// Number of data segments, from the data count section.
+    pub(crate) data_count: Option<u32>,
+}
+
+/// Reads the magic number and version that start every module.
+pub fn decode_preamble<R: Read>(reader: &mut R) -> Result<(), DecodeError> {
+    if read_bytes(reader, 4)? != b"\0asm" {
+        return Err(DecodeError::InvalidMagicNumberError);
+    }
+    if read_bytes(reader, 4)? != [0x01, 0x00, 0x00, 0x00] {
+        return Err(DecodeError::UnknownVersionError);
+    }
+    Ok(())
 }
 
 pub fn decode(buf: &[u8]) -> Result<Sections, DecodeError> {
     let mut cur = Cursor::new(buf);
     let mut sections = Sections {
-        custom_section: None,
+        custom_sections: vec![],
         type_section: None,
         import_section: None,
         function_section: None,
@@ -152,7 +223,10 @@ pub fn decode(buf: &[u8]) -> Result<Sections, DecodeError> {
         start_section: None,
         code_section: None,
         data_section: None,
+        data_count: None,
     };
+    let mut last_order = SectionKind::Custom.order();
+    let mut after = None;
     loop {
         let len = cur.get_ref().len() as u64;
         if cur.position() == len {
@@ -163,10 +237,28 @@ pub fn decode(buf: &[u8]) -> Result<Sections, DecodeError> {
         let mut body = vec![0u8; len];
         cur.read_exact(&mut body)?;
         let mut buf = Cursor::new(body);
-        match num_traits::FromPrimitive::from_u8(kind)
-            .ok_or(DecodeError::InvalidSectionKindFormatError)?
-        {
-            SectionKind::Custom => sections.custom_section = Some(CustomSection::decode(&mut buf)?),
+        let section_kind: SectionKind = num_traits::FromPrimitive::from_u8(kind)
+            .ok_or(DecodeError::InvalidSectionKindFormatError)?;
+        // Custom sections may appear anywhere, others must appear at most once
+        // and in the order given by `SectionKind::order`, which places DataCount
+        // before Code.
+        if section_kind != SectionKind::Custom {
+            let order = section_kind.order();
+            if order == last_order {
+                return Err(DecodeError::DuplicateSectionError);
+            }
+            if order < last_order {
+                return Err(DecodeError::InvalidSectionOrderError);
+            }
+            last_order = order;
+            after = Some(section_kind);
+        }
+        match section_kind {
+            SectionKind::Custom => {
+                let mut custom = CustomSection::decode(&mut buf)?;
+                custom.after = after;
+                sections.custom_sections.push(custom)
+            }
             SectionKind::Type => sections.type_section = Some(TypeSection::decode(&mut buf)?),
             SectionKind::Import => sections.import_section = Some(ImportSection::decode(&mut buf)?),
             SectionKind::Function => {
@@ -182,7 +274,150 @@ pub fn decode(buf: &[u8]) -> Result<Sections, DecodeError> {
             SectionKind::Start => sections.start_section = Some(StartSection::decode(&mut buf)?),
             SectionKind::Code => sections.code_section = Some(CodeSection::decode(&mut buf)?),
             SectionKind::Data => sections.data_section = Some(DataSection::decode(&mut buf)?),
+            SectionKind::DataCount => {
+                sections.data_count = Some(VarUint32::decode(&mut buf)?.into())
+            }
+        }
+        if buf.position() as usize != len {
+            return Err(DecodeError::SectionSizeMismatchError);
+        }
+    }
+    let function_count = sections
+        .function_section
+        .as_ref()
+        .map_or(0, |s| s.types.len());
+    let code_count = sections.code_section.as_ref().map_or(0, |s| s.bodies.len());
+    if function_count != code_count {
+        return Err(DecodeError::FunctionCodeCountMismatchError);
+    }
+    let data_count = sections
+        .data_section
+        .as_ref()
+        .map_or(0, |s| s.segments.len() as u32);
+    match sections.data_count {
+        Some(count) if count != data_count => return Err(DecodeError::DataCountMismatchError),
+        // Data segments can only be referred to by index with the count known up front.
+        None if sections.code_section.iter().any(|c| {
+            c.bodies.iter().any(|b| {
+                b.decoded
+                    .iter()
+                    .any(|(opcode, _)| *opcode == Opcode::MemoryInit || *opcode == Opcode::DataDrop)
+            })
+        }) =>
+        {
+            return Err(DecodeError::DataCountRequiredError)
         }
+        _ => (),
     }
     Ok(sections)
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn keep_every_custom_section() {
+        let b = vec![
+            0x00, 0x03, 0x01, 0x61, 0x01, // custom "a" [1]
+            0x01, 0x01, 0x00, // empty type section
+            0x00, 0x03, 0x01, 0x62, 0x02, // custom "b" [2]
+            0x00, 0x03, 0x01, 0x61, 0x03, // custom "a" [3]
+        ];
+        let sections = decode(&b).unwrap();
+        let custom = |name: &str, payload: u8, after| CustomSection {
+            name: name.to_owned(),
+            payload: vec![payload],
+            after,
+        };
+        assert_eq!(
+            sections.custom_sections,
+            vec![
+                custom("a", 1, None),
+                custom("b", 2, Some(SectionKind::Type)),
+                custom("a", 3, Some(SectionKind::Type)),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_preamble() {
+        let b = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        assert!(decode_preamble(&mut &b[..]).is_ok());
+        let b = [0x00, 0x61, 0x73, 0x6e, 0x01, 0x00, 0x00, 0x00];
+        assert!(matches!(
+            decode_preamble(&mut &b[..]),
+            Err(DecodeError::InvalidMagicNumberError)
+        ));
+        let b = [0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00];
+        assert!(matches!(
+            decode_preamble(&mut &b[..]),
+            Err(DecodeError::UnknownVersionError)
+        ));
+    }
+
+    #[test]
+    fn reject_out_of_order_section() {
+        let b = vec![
+            0x03, 0x01, 0x00, // empty function section
+            0x01, 0x01, 0x00, // empty type section
+        ];
+        assert!(matches!(
+            decode(&b),
+            Err(DecodeError::InvalidSectionOrderError)
+        ));
+    }
+
+    #[test]
+    fn accept_data_count_before_code() {
+        let b = vec![
+            0x0c, 0x01, 0x00, // data count 0
+            0x0a, 0x01, 0x00, // empty code section
+        ];
+        assert_eq!(decode(&b).unwrap().data_count, Some(0));
+        let b = vec![
+            0x0a, 0x01, 0x00, // empty code section
+            0x0c, 0x01, 0x00, // data count 0
+        ];
+        assert!(matches!(
+            decode(&b),
+            Err(DecodeError::InvalidSectionOrderError)
+        ));
+    }
+
+    #[test]
+    fn reject_duplicate_section() {
+        let b = vec![
+            0x01, 0x01, 0x00, // empty type section
+            0x01, 0x01, 0x00, // empty type section
+        ];
+        assert!(matches!(
+            decode(&b),
+            Err(DecodeError::DuplicateSectionError)
+        ));
+    }
+
+    #[test]
+    fn reject_section_size_mismatch() {
+        let b = vec![
+            0x01, 0x02, 0x00, 0x00, // empty type section with a trailing byte
+        ];
+        assert!(matches!(
+            decode(&b),
+            Err(DecodeError::SectionSizeMismatchError)
+        ));
+    }
+
+    #[test]
+    fn reject_function_code_count_mismatch() {
+        let b = vec![
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section [() -> ()]
+            0x03, 0x02, 0x01, 0x00, // function section [0]
+        ];
+        assert!(matches!(
+            decode(&b),
+            Err(DecodeError::FunctionCodeCountMismatchError)
+        ));
+    }
+}
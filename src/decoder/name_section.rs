@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::str::from_utf8;
+
+use crate::reader::*;
+
+use super::number::*;
+use super::{DecodeError, Decoder};
+
+/// Names by index.
+pub type NameMap = HashMap<u32, String>;
+/// Names by index within an outer index, such as locals by function.
+pub type IndirectNameMap = HashMap<u32, NameMap>;
+
+/// The `name` custom section, including the extended name subsections.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameSection {
+    pub module: Option<String>,
+    pub functions: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub memories: NameMap,
+    pub globals: NameMap,
+    pub elem_segments: NameMap,
+    pub data_segments: NameMap,
+}
+
+impl NameSection {
+    pub fn function_name(&self, index: u32) -> Option<&str> {
+        self.functions.get(&index).map(|s| &s[..])
+    }
+
+    pub fn local_name(&self, func_index: u32, index: u32) -> Option<&str> {
+        self.locals
+            .get(&func_index)
+            .and_then(|locals| locals.get(&index))
+            .map(|s| &s[..])
+    }
+
+    /// Index of the function called `name`.
+    pub fn function_index(&self, name: &str) -> Option<u32> {
+        self.functions
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(index, _)| *index)
+    }
+}
+
+fn read_name<R: Read>(reader: &mut R) -> Result<String, DecodeError> {
+    let len: u32 = VarUint32::decode(reader)?.into();
+    let bytes = read_bytes(reader, len as usize)?;
+    Ok(from_utf8(&bytes)?.to_owned())
+}
+
+fn read_name_map<R: Read>(reader: &mut R) -> Result<NameMap, DecodeError> {
+    let count: u32 = VarUint32::decode(reader)?.into();
+    let mut map = HashMap::new();
+    for _ in 0..count {
+        let index: u32 = VarUint32::decode(reader)?.into();
+        map.insert(index, read_name(reader)?);
+    }
+    Ok(map)
+}
+
+fn read_indirect_name_map<R: Read>(reader: &mut R) -> Result<IndirectNameMap, DecodeError> {
+    let count: u32 = VarUint32::decode(reader)?.into();
+    let mut map = HashMap::new();
+    for _ in 0..count {
+        let index: u32 = VarUint32::decode(reader)?.into();
+        map.insert(index, read_name_map(reader)?);
+    }
+    Ok(map)
+}
+
+impl Decoder for NameSection {
+    type Error = DecodeError;
+
+    /// Decodes the payload of the section, that is a sequence of subsections.
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
+        let mut section = NameSection::default();
+        let mut payload = vec![];
+        reader.read_to_end(&mut payload)?;
+        let mut cur = Cursor::new(payload);
+        while (cur.position() as usize) < cur.get_ref().len() {
+            let id = read_next(&mut cur)?;
+            let len: u32 = VarUint32::decode(&mut cur)?.into();
+            let mut sub = Cursor::new(read_bytes(&mut cur, len as usize)?);
+            match id {
+                0 => section.module = Some(read_name(&mut sub)?),
+                1 => section.functions = read_name_map(&mut sub)?,
+                2 => section.locals = read_indirect_name_map(&mut sub)?,
+                3 => section.labels = read_indirect_name_map(&mut sub)?,
+                4 => section.types = read_name_map(&mut sub)?,
+                5 => section.tables = read_name_map(&mut sub)?,
+                6 => section.memories = read_name_map(&mut sub)?,
+                7 => section.globals = read_name_map(&mut sub)?,
+                8 => section.elem_segments = read_name_map(&mut sub)?,
+                9 => section.data_segments = read_name_map(&mut sub)?,
+                // Unknown subsections are skipped.
+                _ => (),
+            }
+        }
+        Ok(section)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn decode_names() {
+        let b = vec![
+            0x00, 0x04, 0x03, 0x6d, 0x6f, 0x64, // module "mod"
+            0x01, 0x05, 0x01, 0x02, 0x02, 0x66, 0x6e, // function 2 "fn"
+            0x02, 0x06, 0x01, 0x02, 0x01, 0x00, 0x01, 0x78, // local 0 of function 2 "x"
+            0x0c, 0x01, 0xff, // unknown subsection
+            0x07, 0x04, 0x01, 0x00, 0x01, 0x67, // global 0 "g"
+        ];
+        let section = NameSection::decode(&mut Cursor::new(b)).unwrap();
+        assert_eq!(section.module, Some("mod".to_owned()));
+        assert_eq!(section.function_name(2), Some("fn"));
+        assert_eq!(section.function_index("fn"), Some(2));
+        assert_eq!(section.local_name(2, 0), Some("x"));
+        assert_eq!(section.globals.get(&0).map(|s| &s[..]), Some("g"));
+        assert_eq!(section.function_name(0), None);
+    }
+}
@@ -43,10 +43,7 @@ impl Decoder for TableSection {
     type Error = DecodeError;
 
     fn decode<R: Read>(reader: &mut R) -> Result<Self, Self::Error> {
-        let count: u8 = VarUint7::decode(reader)?.into();
-        if count > 1 {
-            return Err(DecodeError::InvalidTableCountError);
-        }
+        let count: u32 = VarUint32::decode(reader)?.into();
         let mut entries = vec![];
         for _ in 0..count {
             let table_type = TableType::from_buffer(reader)?;
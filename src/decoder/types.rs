@@ -1,14 +1,27 @@
 use num_derive::*;
 
+use super::InitExpr;
+
 #[derive(Debug, PartialEq)]
 pub struct TypeSection {
-	count: u32,
+    count: u32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum ExternalKind {
-	Function = 0x00,
-	Table = 0x01,
-	Memory = 0x02,
-	Global = 0x03,
+    Function = 0x00,
+    Table = 0x01,
+    Memory = 0x02,
+    Global = 0x03,
+}
+
+/// How a data or element segment is used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SegmentMode {
+    /// Copied into the memory or table `index` at `offset` on instantiation.
+    Active { index: u32, offset: InitExpr },
+    /// Copied only by `memory.init` or `table.init`.
+    Passive,
+    /// Only declares the functions it refers to, never copied.
+    Declarative,
 }
@@ -2,7 +2,8 @@ use std::io;
 use std::string::FromUtf8Error;
 
 use crate::decoder::DecodeError;
-use crate::vm::RuntimeError;
+use crate::validator::ValidationError;
+use crate::vm::{RuntimeError, Trap};
 
 #[derive(Debug, Fail)]
 pub enum YawError {
@@ -12,12 +13,21 @@ pub enum YawError {
     #[fail(display = "{}", message)]
     DecodeError { error: DecodeError, message: String },
 
+    #[fail(display = "{}", message)]
+    ValidationError {
+        error: ValidationError,
+        message: String,
+    },
+
     #[fail(display = "{}", message)]
     RuntimeError {
         error: RuntimeError,
         message: String,
     },
 
+    #[fail(display = "{}", trap)]
+    Trap { trap: Trap },
+
     #[fail(display = "I/O Error: {:?}", error)]
     IOError { error: io::Error },
 
@@ -44,9 +54,27 @@ impl From<DecodeError> for YawError {
     }
 }
 
+impl From<ValidationError> for YawError {
+    fn from(error: ValidationError) -> Self {
+        let message = error.to_string();
+        YawError::ValidationError { error, message }
+    }
+}
+
 impl From<RuntimeError> for YawError {
     fn from(error: RuntimeError) -> Self {
-        let message = error.to_string();
-        YawError::RuntimeError { error, message }
+        match error {
+            RuntimeError::Trap { trap } => YawError::Trap { trap },
+            error => {
+                let message = error.to_string();
+                YawError::RuntimeError { error, message }
+            }
+        }
+    }
+}
+
+impl From<Trap> for YawError {
+    fn from(trap: Trap) -> Self {
+        YawError::Trap { trap }
     }
 }
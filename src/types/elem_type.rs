@@ -1,7 +1,18 @@
 use num_derive::*;
 
+use super::ValueType;
+
 #[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum ElemType {
-  AnyFunc = 0x70,
+    AnyFunc = 0x70,
+    ExternRef = 0x6F,
 }
 
+impl From<ElemType> for ValueType {
+    fn from(t: ElemType) -> ValueType {
+        match t {
+            ElemType::AnyFunc => ValueType::FuncRef,
+            ElemType::ExternRef => ValueType::ExternRef,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::resizable_limits::ResizableLimits;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MemoryType {
-    limits: ResizableLimits,
+    pub limits: ResizableLimits,
 }
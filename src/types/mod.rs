@@ -1,6 +1,7 @@
 pub mod elem_type;
 pub mod memory_type;
 pub mod operand;
+pub mod reference;
 pub mod runtime_value;
 pub mod table_type;
 
@@ -8,7 +9,9 @@ pub(crate) mod resizable_limits;
 pub(crate) mod value_type;
 
 pub use elem_type::*;
+pub use memory_type::*;
 pub use operand::*;
+pub use reference::*;
 pub use resizable_limits::*;
 pub use runtime_value::*;
 pub use table_type::*;
@@ -18,9 +21,25 @@ use num_derive::*;
 
 #[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum ResultType {
-  I32 = 0x7F,
-  I64 = 0x7E,
-  F32 = 0x7D,
-  F64 = 0x7C,
-  Empty = 0x40,
+    I32 = 0x7F,
+    I64 = 0x7E,
+    F32 = 0x7D,
+    F64 = 0x7C,
+    FuncRef = 0x70,
+    ExternRef = 0x6F,
+    Empty = 0x40,
+}
+
+impl ResultType {
+    pub fn value_type(self) -> Option<ValueType> {
+        match self {
+            ResultType::I32 => Some(ValueType::I32),
+            ResultType::I64 => Some(ValueType::I64),
+            ResultType::F32 => Some(ValueType::F32),
+            ResultType::F64 => Some(ValueType::F64),
+            ResultType::FuncRef => Some(ValueType::FuncRef),
+            ResultType::ExternRef => Some(ValueType::ExternRef),
+            ResultType::Empty => None,
+        }
+    }
 }
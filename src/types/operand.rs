@@ -8,6 +8,9 @@ pub enum Operand {
     F32(f32),
     F64(f64),
     ResultType(ResultType),
+    /// Block type given as an index into the type section.
+    TypeIndex(u32),
+    ValueType(ValueType),
 }
 
 impl From<Operand> for RuntimeValue {
@@ -0,0 +1,52 @@
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::vm::FunctionInstanceRef;
+
+/// A non-null `funcref`. References are equal if they refer to the same function.
+#[derive(Debug, Clone)]
+pub struct FuncRef(pub(crate) FunctionInstanceRef);
+
+impl FuncRef {
+    pub fn function(&self) -> &FunctionInstanceRef {
+        &self.0
+    }
+}
+
+impl PartialEq for FuncRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A non-null `externref`, carrying host data that guests can only pass around.
+/// References are equal if they were cloned from the same `ExternRef::new`.
+#[derive(Clone)]
+pub struct ExternRef(Rc<dyn Any>);
+
+impl ExternRef {
+    pub fn new<T: Any>(data: T) -> Self {
+        ExternRef(Rc::new(data))
+    }
+
+    pub fn data(&self) -> &dyn Any {
+        &*self.0
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl PartialEq for ExternRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for ExternRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ExternRef({:p})", Rc::as_ptr(&self.0))
+    }
+}
@@ -124,29 +124,41 @@ impl SignedComparisons for f64 {
 }
 
 macro_rules! compare_int_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::I32(l), RuntimeValue::I32(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                (RuntimeValue::I64(l), RuntimeValue::I64(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! compare_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        (RuntimeValue::F32(l), RuntimeValue::F32(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        (RuntimeValue::F64(l), RuntimeValue::F64(r)) => Ok(RuntimeValue::I32(l.$op(r) as i32)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::I32(l), RuntimeValue::I32(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                (RuntimeValue::I64(l), RuntimeValue::I64(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                (RuntimeValue::F32(l), RuntimeValue::F32(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                (RuntimeValue::F64(l), RuntimeValue::F64(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(r) as i32))
+                }
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 impl RuntimeValue {
@@ -1,12 +1,19 @@
 use crate::types::RuntimeValue;
 use crate::vm::error::*;
+use crate::vm::trap::TrapCode;
 
-trait Trunc<T> {
+pub(crate) trait Trunc<T> {
     fn trunc_s(self) -> Result<T, RuntimeError>;
     fn trunc_u(self) -> Result<T, RuntimeError>;
 }
 
-trait Convert<T> {
+/// Truncation that saturates instead of trapping, and maps NaN to zero.
+pub(crate) trait TruncSat<T> {
+    fn trunc_sat_s(self) -> T;
+    fn trunc_sat_u(self) -> T;
+}
+
+pub(crate) trait Convert<T> {
     fn convert_s(self) -> Result<T, RuntimeError>;
     fn convert_u(self) -> Result<T, RuntimeError>;
 }
@@ -16,12 +23,12 @@ macro_rules! trunc_impl {
         impl Trunc<$signed_to> for $from {
             fn trunc_s(self) -> Result<$signed_to, RuntimeError> {
                 match self {
-                    _n if self.is_nan() => Err(RuntimeError::UnrepresentableIntegerError),
-                    _n if self.is_infinite() => Err(RuntimeError::IntegerOverflowError),
+                    _n if self.is_nan() => Err(TrapCode::UnrepresentableInteger.into()),
+                    _n if self.is_infinite() => Err(TrapCode::IntegerOverflow.into()),
                     n => {
                         let res = n as $signed_to;
                         if ((res as $from) - n.trunc()).abs() > 0.0 {
-                            return Err(RuntimeError::IntegerOverflowError);
+                            return Err(TrapCode::IntegerOverflow.into());
                         }
                         Ok(res)
                     }
@@ -30,12 +37,12 @@ macro_rules! trunc_impl {
 
             fn trunc_u(self) -> Result<$signed_to, RuntimeError> {
                 match self {
-                    _n if self.is_nan() => Err(RuntimeError::UnrepresentableIntegerError),
-                    _n if self.is_infinite() => Err(RuntimeError::IntegerOverflowError),
+                    _n if self.is_nan() => Err(TrapCode::UnrepresentableInteger.into()),
+                    _n if self.is_infinite() => Err(TrapCode::IntegerOverflow.into()),
                     n => {
                         let res = n as $unsigned_to;
                         if ((res as $from) - n.trunc()).abs() > 0.0 {
-                            return Err(RuntimeError::IntegerOverflowError);
+                            return Err(TrapCode::IntegerOverflow.into());
                         }
                         Ok(res as $signed_to)
                     }
@@ -50,6 +57,26 @@ trunc_impl!(f64, i32, u32);
 trunc_impl!(f32, i64, u64);
 trunc_impl!(f64, i64, u64);
 
+macro_rules! trunc_sat_impl {
+    ($from: ty,$signed_to: ty,$unsigned_to: ty) => {
+        impl TruncSat<$signed_to> for $from {
+            // `as` already saturates and turns NaN into zero.
+            fn trunc_sat_s(self) -> $signed_to {
+                self as $signed_to
+            }
+
+            fn trunc_sat_u(self) -> $signed_to {
+                self as $unsigned_to as $signed_to
+            }
+        }
+    };
+}
+
+trunc_sat_impl!(f32, i32, u32);
+trunc_sat_impl!(f64, i32, u32);
+trunc_sat_impl!(f32, i64, u64);
+trunc_sat_impl!(f64, i64, u64);
+
 macro_rules! convert_impl {
     ($from: ty, $mid: ty, $to: ty) => {
         impl Convert<$to> for $from {
@@ -102,6 +129,38 @@ impl RuntimeValue {
         }
     }
 
+    pub fn trunc_sat_s_toi32(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::F32(l) => Ok(RuntimeValue::I32(l.trunc_sat_s())),
+            RuntimeValue::F64(l) => Ok(RuntimeValue::I32(l.trunc_sat_s())),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
+    pub fn trunc_sat_u_toi32(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::F32(l) => Ok(RuntimeValue::I32(l.trunc_sat_u())),
+            RuntimeValue::F64(l) => Ok(RuntimeValue::I32(l.trunc_sat_u())),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
+    pub fn trunc_sat_s_toi64(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::F32(l) => Ok(RuntimeValue::I64(l.trunc_sat_s())),
+            RuntimeValue::F64(l) => Ok(RuntimeValue::I64(l.trunc_sat_s())),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
+    pub fn trunc_sat_u_toi64(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::F32(l) => Ok(RuntimeValue::I64(l.trunc_sat_u())),
+            RuntimeValue::F64(l) => Ok(RuntimeValue::I64(l.trunc_sat_u())),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
     pub fn extend_s(self) -> Result<Self, RuntimeError> {
         match self {
             RuntimeValue::I32(l) => Ok(RuntimeValue::I64(i64::from(l))),
@@ -116,6 +175,32 @@ impl RuntimeValue {
         }
     }
 
+    /// Sign-extends the low 8 bits, keeping the type.
+    pub fn extend8_s(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::I32(l) => Ok(RuntimeValue::I32(i32::from(l as i8))),
+            RuntimeValue::I64(l) => Ok(RuntimeValue::I64(i64::from(l as i8))),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
+    /// Sign-extends the low 16 bits, keeping the type.
+    pub fn extend16_s(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::I32(l) => Ok(RuntimeValue::I32(i32::from(l as i16))),
+            RuntimeValue::I64(l) => Ok(RuntimeValue::I64(i64::from(l as i16))),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
+    /// Sign-extends the low 32 bits of an `i64`.
+    pub fn extend32_s(self) -> Result<Self, RuntimeError> {
+        match self {
+            RuntimeValue::I64(l) => Ok(RuntimeValue::I64(i64::from(l as i32))),
+            _ => Err(RuntimeError::TypeError),
+        }
+    }
+
     pub fn convert_s_tof32(self) -> Result<Self, RuntimeError> {
         match self {
             RuntimeValue::I32(l) => Ok(RuntimeValue::F32(l.convert_s()?)),
@@ -3,61 +3,114 @@ mod conversions;
 mod numeric;
 mod reinterpret;
 
-use super::ValueType;
+pub(crate) use conversions::{Convert, Trunc, TruncSat};
+pub(crate) use numeric::{FloatingNumeric, IntegerNumeric};
 
-#[derive(PartialEq, Clone, Copy, Debug)]
+use std::convert::TryFrom;
+
+use super::{ExternRef, FuncRef, ValueType};
+use crate::vm::error::RuntimeError;
+
+#[derive(PartialEq, Clone, Debug)]
 pub enum RuntimeValue {
-  I32(i32),
-  I64(i64),
-  F32(f32),
-  F64(f64),
-  V128(u128),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    /// A `funcref`, `None` for `ref.null func`.
+    FuncRef(Option<FuncRef>),
+    /// An `externref`, `None` for `ref.null extern`.
+    ExternRef(Option<ExternRef>),
 }
 
-impl From<RuntimeValue> for u32 {
-  fn from(v: RuntimeValue) -> u32 {
-    match v {
-      RuntimeValue::I32(x) => x as u32,
-      RuntimeValue::I64(x) => x as u32,
-      RuntimeValue::F32(x) => x as u32,
-      RuntimeValue::F64(x) => x as u32,
-      RuntimeValue::V128(x) => x as u32,
+impl RuntimeValue {
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            RuntimeValue::FuncRef(None) | RuntimeValue::ExternRef(None)
+        )
     }
-  }
-}
 
-impl From<RuntimeValue> for u64 {
-  fn from(v: RuntimeValue) -> u64 {
-    match v {
-      RuntimeValue::I32(x) => x as u64,
-      RuntimeValue::I64(x) => x as u64,
-      RuntimeValue::F32(x) => x as u64,
-      RuntimeValue::F64(x) => x as u64,
-      RuntimeValue::V128(x) => x as u64,
+    /// Whether the value can be passed where `value_type` is expected.
+    pub fn is_of_type(&self, value_type: ValueType) -> bool {
+        matches!(
+            (self, value_type),
+            (RuntimeValue::I32(_), ValueType::I32)
+                | (RuntimeValue::I64(_), ValueType::I64)
+                | (RuntimeValue::F32(_), ValueType::F32)
+                | (RuntimeValue::F64(_), ValueType::F64)
+                | (RuntimeValue::FuncRef(_), ValueType::FuncRef)
+                | (RuntimeValue::ExternRef(_), ValueType::ExternRef)
+        )
     }
-  }
 }
 
-impl From<RuntimeValue> for usize {
-  fn from(v: RuntimeValue) -> usize {
-    match v {
-      RuntimeValue::I32(x) => x as usize,
-      RuntimeValue::I64(x) => x as usize,
-      RuntimeValue::F32(x) => x as usize,
-      RuntimeValue::F64(x) => x as usize,
-      RuntimeValue::V128(x) => x as usize,
-    }
-  }
+macro_rules! impl_try_from {
+    ($t: ty) => {
+        /// Casts a number to `$t`, failing with `RuntimeError::TypeError` on a reference.
+        impl TryFrom<RuntimeValue> for $t {
+            type Error = RuntimeError;
+
+            fn try_from(v: RuntimeValue) -> Result<$t, RuntimeError> {
+                match v {
+                    RuntimeValue::I32(x) => Ok(x as $t),
+                    RuntimeValue::I64(x) => Ok(x as $t),
+                    RuntimeValue::F32(x) => Ok(x as $t),
+                    RuntimeValue::F64(x) => Ok(x as $t),
+                    RuntimeValue::V128(x) => Ok(x as $t),
+                    RuntimeValue::FuncRef(_) | RuntimeValue::ExternRef(_) => {
+                        Err(RuntimeError::TypeError)
+                    }
+                }
+            }
+        }
+    };
 }
 
+impl_try_from!(u32);
+impl_try_from!(u64);
+impl_try_from!(usize);
+
 impl From<RuntimeValue> for ValueType {
-  fn from(v: RuntimeValue) -> ValueType {
-    match v {
-      RuntimeValue::I32(_) => ValueType::I32,
-      RuntimeValue::I64(_) => ValueType::I64,
-      RuntimeValue::F32(_) => ValueType::F32,
-      RuntimeValue::F64(_) => ValueType::F64,
-      RuntimeValue::V128(_) => unimplemented!("V128 is not implemented."),
+    fn from(v: RuntimeValue) -> ValueType {
+        match v {
+            RuntimeValue::I32(_) => ValueType::I32,
+            RuntimeValue::I64(_) => ValueType::I64,
+            RuntimeValue::F32(_) => ValueType::F32,
+            RuntimeValue::F64(_) => ValueType::F64,
+            RuntimeValue::V128(_) => unimplemented!("V128 is not implemented."),
+            RuntimeValue::FuncRef(_) => ValueType::FuncRef,
+            RuntimeValue::ExternRef(_) => ValueType::ExternRef,
+        }
     }
-  }
 }
+
+/// Extracts the primitive that a statically typed instruction operates on.
+pub trait FromRuntimeValue: Sized {
+    fn from_runtime_value(v: RuntimeValue) -> Option<Self>;
+}
+
+macro_rules! impl_primitive {
+    ($t: ty, $variant: ident) => {
+        impl From<$t> for RuntimeValue {
+            fn from(v: $t) -> RuntimeValue {
+                RuntimeValue::$variant(v)
+            }
+        }
+
+        impl FromRuntimeValue for $t {
+            fn from_runtime_value(v: RuntimeValue) -> Option<$t> {
+                match v {
+                    RuntimeValue::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_primitive!(i32, I32);
+impl_primitive!(i64, I64);
+impl_primitive!(f32, F32);
+impl_primitive!(f64, F64);
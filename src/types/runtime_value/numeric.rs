@@ -1,8 +1,9 @@
 use crate::types::RuntimeValue;
 use crate::vm::error::*;
+use crate::vm::trap::TrapCode;
 use core::ops::{BitAnd, BitOr, BitXor, Neg};
 
-trait IntegerNumeric {
+pub(crate) trait IntegerNumeric {
     fn div_s(&self, rhs: Self) -> Result<Self, RuntimeError>
     where
         Self: Sized;
@@ -27,27 +28,27 @@ trait IntegerNumeric {
 impl IntegerNumeric for i32 {
     fn div_s(&self, rhs: Self) -> Result<Self, RuntimeError> {
         if rhs == 0 {
-            return Err(RuntimeError::DivisionByZeroError);
+            return Err(TrapCode::DivisionByZero.into());
         }
         match (*self).overflowing_div(rhs) {
-            (_, true) => Err(RuntimeError::DivisionOverflowError),
+            (_, true) => Err(TrapCode::IntegerOverflow.into()),
             (res, false) => Ok(res),
         }
     }
 
     fn div_u(&self, rhs: Self) -> Result<Self, RuntimeError> {
         if rhs == 0 {
-            return Err(RuntimeError::DivisionByZeroError);
+            return Err(TrapCode::DivisionByZero.into());
         }
         match (*self as u32).overflowing_div(rhs as u32) {
-            (_, true) => Err(RuntimeError::DivisionOverflowError),
+            (_, true) => Err(TrapCode::IntegerOverflow.into()),
             (res, false) => Ok(res as i32),
         }
     }
 
     fn rem_s(&self, rhs: Self) -> Result<Self, RuntimeError> {
         match rhs {
-            0 => Err(RuntimeError::DivisionByZeroError),
+            0 => Err(TrapCode::DivisionByZero.into()),
             _ => {
                 let (res, _) = self.overflowing_rem(rhs);
                 Ok(res)
@@ -57,7 +58,7 @@ impl IntegerNumeric for i32 {
 
     fn rem_u(&self, rhs: Self) -> Result<Self, RuntimeError> {
         match rhs {
-            0 => Err(RuntimeError::DivisionByZeroError),
+            0 => Err(TrapCode::DivisionByZero.into()),
             _ => {
                 let (res, _) = (*self as u32).overflowing_rem(rhs as u32);
                 Ok(res as i32)
@@ -89,27 +90,27 @@ impl IntegerNumeric for i32 {
 impl IntegerNumeric for i64 {
     fn div_s(&self, rhs: Self) -> Result<Self, RuntimeError> {
         if rhs == 0 {
-            return Err(RuntimeError::DivisionByZeroError);
+            return Err(TrapCode::DivisionByZero.into());
         }
         match self.overflowing_div(rhs) {
-            (_, true) => Err(RuntimeError::DivisionOverflowError),
+            (_, true) => Err(TrapCode::IntegerOverflow.into()),
             (res, false) => Ok(res as i64),
         }
     }
 
     fn div_u(&self, rhs: Self) -> Result<Self, RuntimeError> {
         if rhs == 0 {
-            return Err(RuntimeError::DivisionByZeroError);
+            return Err(TrapCode::DivisionByZero.into());
         }
         match (*self as u64).overflowing_div(rhs as u64) {
-            (_, true) => Err(RuntimeError::DivisionOverflowError),
+            (_, true) => Err(TrapCode::IntegerOverflow.into()),
             (res, false) => Ok(res as i64),
         }
     }
 
     fn rem_s(&self, rhs: Self) -> Result<Self, RuntimeError> {
         match rhs {
-            0 => Err(RuntimeError::DivisionByZeroError),
+            0 => Err(TrapCode::DivisionByZero.into()),
             _ => {
                 let (res, _) = self.overflowing_rem(rhs);
                 Ok(res)
@@ -119,7 +120,7 @@ impl IntegerNumeric for i64 {
 
     fn rem_u(&self, rhs: Self) -> Result<Self, RuntimeError> {
         match rhs {
-            0 => Err(RuntimeError::DivisionByZeroError),
+            0 => Err(TrapCode::DivisionByZero.into()),
             _ => {
                 let (res, _) = (*self as u64).overflowing_rem(rhs as u64);
                 Ok(res as i64)
@@ -148,7 +149,7 @@ impl IntegerNumeric for i64 {
     }
 }
 
-trait FloatingNumeric {
+pub(crate) trait FloatingNumeric {
     fn wrapping_add(self, x: Self) -> Self;
     fn wrapping_sub(self, x: Self) -> Self;
     fn wrapping_mul(self, x: Self) -> Self;
@@ -204,77 +205,89 @@ impl_float_operation!(f32);
 impl_float_operation!(f64);
 
 macro_rules! binary_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(*r))),
-        (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I64(l.$op(*r))),
-        (RuntimeValue::F32(l), RuntimeValue::F32(r)) => Ok(RuntimeValue::F32(l.$op(*r))),
-        (RuntimeValue::F64(l), RuntimeValue::F64(r)) => Ok(RuntimeValue::F64(l.$op(*r))),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(*r))),
+                (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I64(l.$op(*r))),
+                (RuntimeValue::F32(l), RuntimeValue::F32(r)) => Ok(RuntimeValue::F32(l.$op(*r))),
+                (RuntimeValue::F64(l), RuntimeValue::F64(r)) => Ok(RuntimeValue::F64(l.$op(*r))),
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! unary_int_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self) -> Result<Self, RuntimeError> {
-      match self {
-        RuntimeValue::I32(l) => Ok(RuntimeValue::I32(l.$op() as i32)),
-        RuntimeValue::I64(l) => Ok(RuntimeValue::I64(l.$op() as i64)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self) -> Result<Self, RuntimeError> {
+            match self {
+                RuntimeValue::I32(l) => Ok(RuntimeValue::I32(l.$op() as i32)),
+                RuntimeValue::I64(l) => Ok(RuntimeValue::I64(l.$op() as i64)),
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! unary_float_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self) -> Result<Self, RuntimeError> {
-      match self {
-        RuntimeValue::F32(l) => Ok(RuntimeValue::F32(l.$op() as f32)),
-        RuntimeValue::F64(l) => Ok(RuntimeValue::F64(l.$op() as f64)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self) -> Result<Self, RuntimeError> {
+            match self {
+                RuntimeValue::F32(l) => Ok(RuntimeValue::F32(l.$op() as f32)),
+                RuntimeValue::F64(l) => Ok(RuntimeValue::F64(l.$op() as f64)),
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! binary_int_div_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(*r)? as i32)),
-        (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I64(l.$op(*r)? as i64)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::I32(l), RuntimeValue::I32(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(*r)? as i32))
+                }
+                (RuntimeValue::I64(l), RuntimeValue::I64(r)) => {
+                    Ok(RuntimeValue::I64(l.$op(*r)? as i64))
+                }
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! binary_int_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::I32(l), RuntimeValue::I32(r)) => Ok(RuntimeValue::I32(l.$op(*r) as i32)),
-        (RuntimeValue::I64(l), RuntimeValue::I64(r)) => Ok(RuntimeValue::I64(l.$op(*r) as i64)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::I32(l), RuntimeValue::I32(r)) => {
+                    Ok(RuntimeValue::I32(l.$op(*r) as i32))
+                }
+                (RuntimeValue::I64(l), RuntimeValue::I64(r)) => {
+                    Ok(RuntimeValue::I64(l.$op(*r) as i64))
+                }
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 macro_rules! binary_float_operation {
-  ($fn_name: ident,$op: ident) => {
-    pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
-      match (self, rhs) {
-        (RuntimeValue::F32(l), RuntimeValue::F32(r)) => Ok(RuntimeValue::F32(l.$op(*r) as f32)),
-        (RuntimeValue::F64(l), RuntimeValue::F64(r)) => Ok(RuntimeValue::F64(l.$op(*r) as f64)),
-        _ => Err(RuntimeError::TypeError),
-      }
-    }
-  };
+    ($fn_name: ident,$op: ident) => {
+        pub fn $fn_name(&self, rhs: &Self) -> Result<Self, RuntimeError> {
+            match (self, rhs) {
+                (RuntimeValue::F32(l), RuntimeValue::F32(r)) => {
+                    Ok(RuntimeValue::F32(l.$op(*r) as f32))
+                }
+                (RuntimeValue::F64(l), RuntimeValue::F64(r)) => {
+                    Ok(RuntimeValue::F64(l.$op(*r) as f64))
+                }
+                _ => Err(RuntimeError::TypeError),
+            }
+        }
+    };
 }
 
 impl RuntimeValue {
@@ -308,6 +321,7 @@ impl RuntimeValue {
     binary_float_operation!(max, max);
     binary_float_operation!(copysign, copysign);
 
+    /// Whether the value is a numeric zero. References are never zero.
     pub fn is_zero(self) -> bool {
         match self {
             RuntimeValue::I32(v) => v == 0,
@@ -315,6 +329,7 @@ impl RuntimeValue {
             RuntimeValue::F32(v) => v == 0.0,
             RuntimeValue::F64(v) => v == 0.0,
             RuntimeValue::V128(v) => v == 0,
+            RuntimeValue::FuncRef(_) | RuntimeValue::ExternRef(_) => false,
         }
     }
 }
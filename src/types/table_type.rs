@@ -1,7 +1,7 @@
 use super::elem_type::ElemType;
 use super::resizable_limits::ResizableLimits;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TableType {
     pub element_type: ElemType,
     pub limits: ResizableLimits,
@@ -0,0 +1,698 @@
+use crate::decoder::*;
+use crate::types::*;
+use crate::vm::Opcode;
+
+use super::{Context, ValidationError};
+
+use ValueType::*;
+
+#[derive(Debug)]
+struct ControlFrame {
+    opcode: Opcode,
+    start_types: Vec<ValueType>,
+    end_types: Vec<ValueType>,
+    height: usize,
+    unreachable: bool,
+}
+
+impl ControlFrame {
+    fn label_types(&self) -> &[ValueType] {
+        if self.opcode == Opcode::Loop {
+            &self.start_types
+        } else {
+            &self.end_types
+        }
+    }
+}
+
+/// Type checks a function body with the algorithm described in the appendix of
+/// the WebAssembly specification. `None` on the operand stack is an unknown
+/// type, which appears only in unreachable code.
+pub(crate) struct FunctionValidator<'a> {
+    ctx: &'a Context,
+    func_type: &'a FuncType,
+    locals: &'a [LocalEntry],
+    operands: Vec<Option<ValueType>>,
+    frames: Vec<ControlFrame>,
+}
+
+impl<'a> FunctionValidator<'a> {
+    pub fn new(ctx: &'a Context, func_type: &'a FuncType, locals: &'a [LocalEntry]) -> Self {
+        Self {
+            ctx,
+            func_type,
+            locals,
+            operands: vec![],
+            frames: vec![],
+        }
+    }
+
+    pub fn validate(mut self, instructions: &[Instruction]) -> Result<(), ValidationError> {
+        let results = self.func_type.results.clone();
+        self.push_frame(Opcode::Block, vec![], results);
+        for (opcode, operands) in instructions {
+            self.step(*opcode, operands)?;
+        }
+        // The final `end` of the body is not kept by the decoder.
+        self.pop_frame()?;
+        if !self.frames.is_empty() {
+            return Err(ValidationError::TypeMismatchError);
+        }
+        Ok(())
+    }
+
+    fn local(&self, index: u32) -> Result<ValueType, ValidationError> {
+        let args = &self.func_type.args;
+        if let Some(t) = args.get(index as usize) {
+            return Ok(*t);
+        }
+        let mut rest = u64::from(index) - args.len() as u64;
+        for entry in self.locals {
+            if rest < u64::from(entry.count) {
+                return Ok(entry.value_type);
+            }
+            rest -= u64::from(entry.count);
+        }
+        Err(ValidationError::UnknownLocalError { index })
+    }
+
+    fn push(&mut self, t: ValueType) {
+        self.operands.push(Some(t));
+    }
+
+    fn push_all(&mut self, types: &[ValueType]) {
+        for t in types {
+            self.push(*t);
+        }
+    }
+
+    fn pop(&mut self) -> Result<Option<ValueType>, ValidationError> {
+        let frame = self
+            .frames
+            .last()
+            .ok_or(ValidationError::TypeMismatchError)?;
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err(ValidationError::TypeMismatchError);
+        }
+        Ok(self.operands.pop().flatten())
+    }
+
+    fn pop_expect(&mut self, expected: ValueType) -> Result<Option<ValueType>, ValidationError> {
+        match self.pop()? {
+            Some(actual) if actual != expected => Err(ValidationError::TypeMismatchError),
+            actual => Ok(actual.or(Some(expected))),
+        }
+    }
+
+    fn pop_all(&mut self, types: &[ValueType]) -> Result<(), ValidationError> {
+        for t in types.iter().rev() {
+            self.pop_expect(*t)?;
+        }
+        Ok(())
+    }
+
+    fn push_frame(
+        &mut self,
+        opcode: Opcode,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+    ) {
+        let height = self.operands.len();
+        self.push_all(&start_types);
+        self.frames.push(ControlFrame {
+            opcode,
+            start_types,
+            end_types,
+            height,
+            unreachable: false,
+        });
+    }
+
+    fn pop_frame(&mut self) -> Result<ControlFrame, ValidationError> {
+        let end_types = self
+            .frames
+            .last()
+            .ok_or(ValidationError::TypeMismatchError)?
+            .end_types
+            .clone();
+        self.pop_all(&end_types)?;
+        let frame = self
+            .frames
+            .pop()
+            .ok_or(ValidationError::TypeMismatchError)?;
+        if self.operands.len() != frame.height {
+            return Err(ValidationError::TypeMismatchError);
+        }
+        Ok(frame)
+    }
+
+    fn label_types(&self, depth: u32) -> Result<Vec<ValueType>, ValidationError> {
+        let len = self.frames.len();
+        if depth as usize >= len {
+            return Err(ValidationError::UnknownLabelError { depth });
+        }
+        Ok(self.frames[len - 1 - depth as usize].label_types().to_vec())
+    }
+
+    fn unreachable(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            self.operands.truncate(frame.height);
+            frame.unreachable = true;
+        }
+    }
+
+    fn unary(&mut self, arg: ValueType, result: ValueType) -> Result<(), ValidationError> {
+        self.pop_expect(arg)?;
+        self.push(result);
+        Ok(())
+    }
+
+    fn pop_n(&mut self, arg: ValueType, n: usize) -> Result<(), ValidationError> {
+        for _ in 0..n {
+            self.pop_expect(arg)?;
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, arg: ValueType, result: ValueType) -> Result<(), ValidationError> {
+        self.pop_expect(arg)?;
+        self.pop_expect(arg)?;
+        self.push(result);
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        operands: &[Operand],
+        natural: u32,
+        result: ValueType,
+    ) -> Result<(), ValidationError> {
+        self.memarg(operands, natural)?;
+        self.unary(I32, result)
+    }
+
+    fn store(
+        &mut self,
+        operands: &[Operand],
+        natural: u32,
+        value: ValueType,
+    ) -> Result<(), ValidationError> {
+        self.memarg(operands, natural)?;
+        self.pop_expect(value)?;
+        self.pop_expect(I32)?;
+        Ok(())
+    }
+
+    fn memarg(&self, operands: &[Operand], natural: u32) -> Result<(), ValidationError> {
+        self.ctx.memory(0)?;
+        let align: u32 = operands[0].into();
+        if align > natural {
+            return Err(ValidationError::InvalidAlignmentError);
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, func_type: &FuncType) -> Result<(), ValidationError> {
+        self.pop_all(&func_type.args)?;
+        self.push_all(&func_type.results);
+        Ok(())
+    }
+
+    /// The params and results of a block.
+    fn block_type(
+        &self,
+        operands: &[Operand],
+    ) -> Result<(Vec<ValueType>, Vec<ValueType>), ValidationError> {
+        match operands[0] {
+            Operand::ResultType(t) => Ok((vec![], t.value_type().into_iter().collect())),
+            Operand::TypeIndex(index) => {
+                let func_type = self.ctx.func_type(index)?;
+                Ok((func_type.args.clone(), func_type.results.clone()))
+            }
+            _ => Ok((vec![], vec![])),
+        }
+    }
+
+    fn step(&mut self, opcode: Opcode, operands: &[Operand]) -> Result<(), ValidationError> {
+        match opcode {
+            Opcode::Unreachable => self.unreachable(),
+            Opcode::Nop => {}
+            Opcode::Block | Opcode::Loop => {
+                let (params, results) = self.block_type(operands)?;
+                self.pop_all(&params)?;
+                self.push_frame(opcode, params, results);
+            }
+            Opcode::If => {
+                self.pop_expect(I32)?;
+                let (params, results) = self.block_type(operands)?;
+                self.pop_all(&params)?;
+                self.push_frame(opcode, params, results);
+            }
+            Opcode::Else => {
+                let frame = self.pop_frame()?;
+                if frame.opcode != Opcode::If {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                self.push_frame(Opcode::Else, frame.start_types, frame.end_types);
+            }
+            Opcode::End => {
+                let frame = self.pop_frame()?;
+                // An `if` without `else` has to leave its params as results.
+                if frame.opcode == Opcode::If && frame.start_types != frame.end_types {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                self.push_all(&frame.end_types);
+            }
+            Opcode::Br => {
+                let depth: u32 = operands[0].into();
+                let types = self.label_types(depth)?;
+                self.pop_all(&types)?;
+                self.unreachable();
+            }
+            Opcode::BrIf => {
+                let depth: u32 = operands[0].into();
+                self.pop_expect(I32)?;
+                let types = self.label_types(depth)?;
+                self.pop_all(&types)?;
+                self.push_all(&types);
+            }
+            Opcode::BrTable => {
+                let count: usize = operands[0].into();
+                let default: u32 = operands[count + 1].into();
+                self.pop_expect(I32)?;
+                let default_types = self.label_types(default)?;
+                for operand in &operands[1..=count] {
+                    let depth: u32 = (*operand).into();
+                    if self.label_types(depth)?.len() != default_types.len() {
+                        return Err(ValidationError::TypeMismatchError);
+                    }
+                    let types = self.label_types(depth)?;
+                    self.pop_all(&types)?;
+                    self.push_all(&types);
+                }
+                self.pop_all(&default_types)?;
+                self.unreachable();
+            }
+            Opcode::Return => {
+                let results = self.func_type.results.clone();
+                self.pop_all(&results)?;
+                self.unreachable();
+            }
+            Opcode::Call => {
+                let index: u32 = operands[0].into();
+                let func_type = self.ctx.function(index)?;
+                self.call(func_type)?;
+            }
+            Opcode::CallIndirect => {
+                let index: u32 = operands[0].into();
+                if self.ctx.table(operands[1].into())? != ValueType::FuncRef {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                let func_type = self.ctx.func_type(index)?;
+                self.pop_expect(I32)?;
+                self.call(func_type)?;
+            }
+            Opcode::Drop => {
+                self.pop()?;
+            }
+            Opcode::Select => {
+                self.pop_expect(I32)?;
+                let first = self.pop()?;
+                let second = self.pop()?;
+                match (first, second) {
+                    (Some(a), Some(b)) if a != b => return Err(ValidationError::TypeMismatchError),
+                    // References need a typed `select`.
+                    (Some(t), _) | (None, Some(t)) if t.is_ref() => {
+                        return Err(ValidationError::TypeMismatchError)
+                    }
+                    (Some(t), _) | (None, Some(t)) => self.push(t),
+                    (None, None) => self.operands.push(None),
+                }
+            }
+            Opcode::TypedSelect => {
+                let t = match operands {
+                    [Operand::ValueType(t)] => *t,
+                    _ => return Err(ValidationError::InvalidResultArityError),
+                };
+                self.pop_expect(I32)?;
+                self.pop_expect(t)?;
+                self.pop_expect(t)?;
+                self.push(t);
+            }
+            Opcode::RefNull => {
+                if let Operand::ValueType(t) = operands[0] {
+                    self.push(t);
+                }
+            }
+            Opcode::RefIsNull => {
+                if let Some(t) = self.pop()? {
+                    if !t.is_ref() {
+                        return Err(ValidationError::TypeMismatchError);
+                    }
+                }
+                self.push(I32);
+            }
+            Opcode::RefFunc => {
+                self.ctx.func_ref(operands[0].into())?;
+                self.push(ValueType::FuncRef);
+            }
+            Opcode::TableGet => {
+                let t = self.ctx.table(operands[0].into())?;
+                self.unary(I32, t)?;
+            }
+            Opcode::TableSet => {
+                let t = self.ctx.table(operands[0].into())?;
+                self.pop_expect(t)?;
+                self.pop_expect(I32)?;
+            }
+            Opcode::TableSize => {
+                self.ctx.table(operands[0].into())?;
+                self.push(I32);
+            }
+            Opcode::TableGrow => {
+                let t = self.ctx.table(operands[0].into())?;
+                self.pop_expect(I32)?;
+                self.pop_expect(t)?;
+                self.push(I32);
+            }
+            Opcode::TableFill => {
+                let t = self.ctx.table(operands[0].into())?;
+                self.pop_expect(I32)?;
+                self.pop_expect(t)?;
+                self.pop_expect(I32)?;
+            }
+            Opcode::GetLocal => {
+                let t = self.local(operands[0].into())?;
+                self.push(t);
+            }
+            Opcode::SetLocal => {
+                let t = self.local(operands[0].into())?;
+                self.pop_expect(t)?;
+            }
+            Opcode::TeeLocal => {
+                let t = self.local(operands[0].into())?;
+                self.unary(t, t)?;
+            }
+            Opcode::GetGlobal => {
+                let t = self.ctx.global(operands[0].into())?.value_type;
+                self.push(t);
+            }
+            Opcode::SetGlobal => {
+                let global = self.ctx.global(operands[0].into())?;
+                if !global.mutability {
+                    return Err(ValidationError::ImmutableGlobalError);
+                }
+                let t = global.value_type;
+                self.pop_expect(t)?;
+            }
+            Opcode::I32Load => self.load(operands, 2, I32)?,
+            Opcode::I64Load => self.load(operands, 3, I64)?,
+            Opcode::F32Load => self.load(operands, 2, F32)?,
+            Opcode::F64Load => self.load(operands, 3, F64)?,
+            Opcode::I32Load8S | Opcode::I32Load8U => self.load(operands, 0, I32)?,
+            Opcode::I32Load16S | Opcode::I32Load16U => self.load(operands, 1, I32)?,
+            Opcode::I64Load8S | Opcode::I64Load8U => self.load(operands, 0, I64)?,
+            Opcode::I64Load16S | Opcode::I64Load16U => self.load(operands, 1, I64)?,
+            Opcode::I64Load32S | Opcode::I64Load32U => self.load(operands, 2, I64)?,
+            Opcode::I32Store => self.store(operands, 2, I32)?,
+            Opcode::I64Store => self.store(operands, 3, I64)?,
+            Opcode::F32Store => self.store(operands, 2, F32)?,
+            Opcode::F64Store => self.store(operands, 3, F64)?,
+            Opcode::I32Store8 => self.store(operands, 0, I32)?,
+            Opcode::I32Store16 => self.store(operands, 1, I32)?,
+            Opcode::I64Store8 => self.store(operands, 0, I64)?,
+            Opcode::I64Store16 => self.store(operands, 1, I64)?,
+            Opcode::I64Store32 => self.store(operands, 2, I64)?,
+            Opcode::CurrentMemory => {
+                self.ctx.memory(0)?;
+                self.push(I32);
+            }
+            Opcode::GrowMemory => {
+                self.ctx.memory(0)?;
+                self.unary(I32, I32)?;
+            }
+            Opcode::MemoryInit => {
+                self.ctx.memory(0)?;
+                self.ctx.data_segment(operands[0].into())?;
+                self.pop_n(I32, 3)?;
+            }
+            Opcode::DataDrop => self.ctx.data_segment(operands[0].into())?,
+            Opcode::MemoryCopy | Opcode::MemoryFill => {
+                self.ctx.memory(0)?;
+                self.pop_n(I32, 3)?;
+            }
+            Opcode::TableInit => {
+                let elem_type = self.ctx.elem_segment(operands[0].into())?;
+                if self.ctx.table(operands[1].into())? != elem_type {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                self.pop_n(I32, 3)?;
+            }
+            Opcode::ElemDrop => {
+                self.ctx.elem_segment(operands[0].into())?;
+            }
+            Opcode::TableCopy => {
+                if self.ctx.table(operands[0].into())? != self.ctx.table(operands[1].into())? {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                self.pop_n(I32, 3)?;
+            }
+            Opcode::I32Const => self.push(I32),
+            Opcode::I64Const => self.push(I64),
+            Opcode::F32Const => self.push(F32),
+            Opcode::F64Const => self.push(F64),
+            Opcode::I32Eqz => self.unary(I32, I32)?,
+            Opcode::I64Eqz => self.unary(I64, I32)?,
+            Opcode::I32Eq
+            | Opcode::I32Ne
+            | Opcode::I32LtS
+            | Opcode::I32LtU
+            | Opcode::I32GtS
+            | Opcode::I32GtU
+            | Opcode::I32LeS
+            | Opcode::I32LeU
+            | Opcode::I32GeS
+            | Opcode::I32GeU => self.binary(I32, I32)?,
+            Opcode::I64Eq
+            | Opcode::I64Ne
+            | Opcode::I64LtS
+            | Opcode::I64LtU
+            | Opcode::I64GtS
+            | Opcode::I64GtU
+            | Opcode::I64LeS
+            | Opcode::I64LeU
+            | Opcode::I64GeS
+            | Opcode::I64GeU => self.binary(I64, I32)?,
+            Opcode::F32Eq
+            | Opcode::F32Ne
+            | Opcode::F32Lt
+            | Opcode::F32Gt
+            | Opcode::F32Le
+            | Opcode::F32Ge => self.binary(F32, I32)?,
+            Opcode::F64Eq
+            | Opcode::F64Ne
+            | Opcode::F64Lt
+            | Opcode::F64Gt
+            | Opcode::F64Le
+            | Opcode::F64Ge => self.binary(F64, I32)?,
+            Opcode::I32Clz | Opcode::I32Ctz | Opcode::I32Popcnt => self.unary(I32, I32)?,
+            Opcode::I32Add
+            | Opcode::I32Sub
+            | Opcode::I32Mul
+            | Opcode::I32DivS
+            | Opcode::I32DivU
+            | Opcode::I32RemS
+            | Opcode::I32RemU
+            | Opcode::I32And
+            | Opcode::I32Or
+            | Opcode::I32Xor
+            | Opcode::I32Shl
+            | Opcode::I32ShrS
+            | Opcode::I32ShrU
+            | Opcode::I32Rotl
+            | Opcode::I32Rotr => self.binary(I32, I32)?,
+            Opcode::I64Clz | Opcode::I64Ctz | Opcode::I64Popcnt => self.unary(I64, I64)?,
+            Opcode::I64Add
+            | Opcode::I64Sub
+            | Opcode::I64Mul
+            | Opcode::I64DivS
+            | Opcode::I64DivU
+            | Opcode::I64RemS
+            | Opcode::I64RemU
+            | Opcode::I64And
+            | Opcode::I64Or
+            | Opcode::I64Xor
+            | Opcode::I64Shl
+            | Opcode::I64ShrS
+            | Opcode::I64ShrU
+            | Opcode::I64Rotl
+            | Opcode::I64Rotr => self.binary(I64, I64)?,
+            Opcode::F32Abs
+            | Opcode::F32Neg
+            | Opcode::F32Ceil
+            | Opcode::F32Floor
+            | Opcode::F32Trunc
+            | Opcode::F32Nearest
+            | Opcode::F32Sqrt => self.unary(F32, F32)?,
+            Opcode::F32Add
+            | Opcode::F32Sub
+            | Opcode::F32Mul
+            | Opcode::F32Div
+            | Opcode::F32Min
+            | Opcode::F32Max
+            | Opcode::F32Copysign => self.binary(F32, F32)?,
+            Opcode::F64Abs
+            | Opcode::F64Neg
+            | Opcode::F64Ceil
+            | Opcode::F64Floor
+            | Opcode::F64Trunc
+            | Opcode::F64Nearest
+            | Opcode::F64Sqrt => self.unary(F64, F64)?,
+            Opcode::F64Add
+            | Opcode::F64Sub
+            | Opcode::F64Mul
+            | Opcode::F64Div
+            | Opcode::F64Min
+            | Opcode::F64Max
+            | Opcode::F64Copysign => self.binary(F64, F64)?,
+            Opcode::I32WrapI64 => self.unary(I64, I32)?,
+            Opcode::I32TruncSF32 | Opcode::I32TruncUF32 => self.unary(F32, I32)?,
+            Opcode::I32TruncSF64 | Opcode::I32TruncUF64 => self.unary(F64, I32)?,
+            Opcode::I64ExtendSI32 | Opcode::I64ExtendUI32 => self.unary(I32, I64)?,
+            Opcode::I64TruncSF32 | Opcode::I64TruncUF32 => self.unary(F32, I64)?,
+            Opcode::I64TruncSF64 | Opcode::I64TruncUF64 => self.unary(F64, I64)?,
+            Opcode::F32ConvertSI32 | Opcode::F32ConvertUI32 => self.unary(I32, F32)?,
+            Opcode::F32ConvertSI64 | Opcode::F32ConvertUI64 => self.unary(I64, F32)?,
+            Opcode::F32DemoteF64 => self.unary(F64, F32)?,
+            Opcode::F64ConvertSI32 | Opcode::F64ConvertUI32 => self.unary(I32, F64)?,
+            Opcode::F64ConvertSI64 | Opcode::F64ConvertUI64 => self.unary(I64, F64)?,
+            Opcode::F64PromoteF32 => self.unary(F32, F64)?,
+            Opcode::I32ReinterpretF32 => self.unary(F32, I32)?,
+            Opcode::I64ReinterpretF64 => self.unary(F64, I64)?,
+            Opcode::F32ReinterpretI32 => self.unary(I32, F32)?,
+            Opcode::F64ReinterpretI64 => self.unary(I64, F64)?,
+            Opcode::I32Extend8S | Opcode::I32Extend16S => self.unary(I32, I32)?,
+            Opcode::I32TruncSatSF32 | Opcode::I32TruncSatUF32 => self.unary(F32, I32)?,
+            Opcode::I32TruncSatSF64 | Opcode::I32TruncSatUF64 => self.unary(F64, I32)?,
+            Opcode::I64TruncSatSF32 | Opcode::I64TruncSatUF32 => self.unary(F32, I64)?,
+            Opcode::I64TruncSatSF64 | Opcode::I64TruncSatUF64 => self.unary(F64, I64)?,
+            Opcode::I64Extend8S | Opcode::I64Extend16S | Opcode::I64Extend32S => {
+                self.unary(I64, I64)?
+            }
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn validate(
+        args: Vec<ValueType>,
+        results: Vec<ValueType>,
+        instructions: Vec<Instruction>,
+    ) -> Result<(), ValidationError> {
+        let ctx = Context::default();
+        let func_type = FuncType { args, results };
+        FunctionValidator::new(&ctx, &func_type, &[]).validate(&instructions)
+    }
+
+    #[test]
+    fn it_validate_add() {
+        let res = validate(
+            vec![I32, I32],
+            vec![I32],
+            vec![
+                (Opcode::GetLocal, vec![Operand::U32(0)]),
+                (Opcode::GetLocal, vec![Operand::U32(1)]),
+                (Opcode::I32Add, vec![]),
+            ],
+        );
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn it_reject_type_mismatch() {
+        let res = validate(
+            vec![I32, I64],
+            vec![I32],
+            vec![
+                (Opcode::GetLocal, vec![Operand::U32(0)]),
+                (Opcode::GetLocal, vec![Operand::U32(1)]),
+                (Opcode::I32Add, vec![]),
+            ],
+        );
+        assert!(match res {
+            Err(ValidationError::TypeMismatchError) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn it_reject_unknown_label() {
+        let res = validate(
+            vec![],
+            vec![],
+            vec![
+                (Opcode::Block, vec![Operand::ResultType(ResultType::Empty)]),
+                (Opcode::Br, vec![Operand::U32(2)]),
+                (Opcode::End, vec![]),
+            ],
+        );
+        assert!(match res {
+            Err(ValidationError::UnknownLabelError { depth: 2 }) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn it_accept_unreachable_code() {
+        // (func (result i32) (block (result i32) (br 0 (i32.const 1)) (i64.const 1) (i32.add)))
+        let res = validate(
+            vec![],
+            vec![I32],
+            vec![
+                (Opcode::Block, vec![Operand::ResultType(ResultType::I32)]),
+                (Opcode::I32Const, vec![Operand::I32(1)]),
+                (Opcode::Br, vec![Operand::U32(0)]),
+                (Opcode::I32Add, vec![]),
+                (Opcode::End, vec![]),
+            ],
+        );
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn it_validate_multi_value_block() {
+        // (func (result i32 i64)
+        //   (i32.const 1) (block (type 0) (param i32) (result i32 i64) (i64.const 2)))
+        let mut ctx = Context::default();
+        ctx.types = vec![FuncType {
+            args: vec![I32],
+            results: vec![I32, I64],
+        }];
+        let func_type = FuncType {
+            args: vec![],
+            results: vec![I32, I64],
+        };
+        let instructions = vec![
+            (Opcode::I32Const, vec![Operand::I32(1)]),
+            (Opcode::Block, vec![Operand::TypeIndex(0)]),
+            (Opcode::I64Const, vec![Operand::I64(2)]),
+            (Opcode::End, vec![]),
+        ];
+        let res = FunctionValidator::new(&ctx, &func_type, &[]).validate(&instructions);
+        assert!(res.is_ok());
+        // The param of the block is missing.
+        let res = FunctionValidator::new(&ctx, &func_type, &[]).validate(&instructions[1..]);
+        assert!(match res {
+            Err(ValidationError::TypeMismatchError) => true,
+            _ => false,
+        });
+    }
+}
@@ -0,0 +1,374 @@
+mod function;
+
+use std::collections::HashSet;
+
+use crate::decoder::*;
+use crate::types::*;
+use crate::vm::Opcode;
+
+use function::FunctionValidator;
+
+const MAX_PAGES: u32 = 0x10000;
+
+#[derive(Debug, Fail)]
+pub enum ValidationError {
+    #[fail(display = "type mismatch")]
+    TypeMismatchError,
+
+    #[fail(display = "unknown local {}", index)]
+    UnknownLocalError { index: u32 },
+
+    #[fail(display = "unknown global {}", index)]
+    UnknownGlobalError { index: u32 },
+
+    #[fail(display = "unknown function {}", index)]
+    UnknownFunctionError { index: u32 },
+
+    #[fail(display = "unknown type {}", index)]
+    UnknownTypeError { index: u32 },
+
+    #[fail(display = "unknown table {}", index)]
+    UnknownTableError { index: u32 },
+
+    #[fail(display = "unknown memory {}", index)]
+    UnknownMemoryError { index: u32 },
+
+    #[fail(display = "unknown data segment {}", index)]
+    UnknownDataSegmentError { index: u32 },
+
+    #[fail(display = "unknown elem segment {}", index)]
+    UnknownElemSegmentError { index: u32 },
+
+    #[fail(display = "unknown label {}", depth)]
+    UnknownLabelError { depth: u32 },
+
+    #[fail(display = "global is immutable")]
+    ImmutableGlobalError,
+
+    #[fail(display = "alignment must not be larger than natural")]
+    InvalidAlignmentError,
+
+    #[fail(display = "invalid result arity")]
+    InvalidResultArityError,
+
+    #[fail(display = "multiple memories")]
+    MultipleMemoriesError,
+
+    #[fail(display = "size minimum must not be greater than maximum")]
+    InvalidLimitsError,
+
+    #[fail(display = "memory size must be at most 65536 pages (4GiB)")]
+    InvalidMemorySizeError,
+
+    #[fail(display = "duplicate export name {}", name)]
+    DuplicateExportError { name: String },
+
+    #[fail(display = "start function")]
+    InvalidStartFunctionError,
+
+    #[fail(display = "constant expression required")]
+    ConstantExpressionRequiredError,
+
+    #[fail(display = "undeclared function reference")]
+    UndeclaredFunctionReferenceError,
+}
+
+/// Everything the body of a function may refer to, in index space order
+/// (imported entities first, then the ones defined by the module).
+#[derive(Debug, Default)]
+pub(crate) struct Context {
+    pub types: Vec<FuncType>,
+    pub functions: Vec<u32>,
+    pub globals: Vec<GlobalType>,
+    /// Element types of the tables.
+    pub tables: Vec<ValueType>,
+    pub memories: usize,
+    pub imported_globals: usize,
+    pub data_segments: usize,
+    /// Element types of the element segments.
+    pub elem_segments: Vec<ValueType>,
+    /// Functions that `ref.func` may refer to.
+    pub refs: HashSet<u32>,
+}
+
+impl Context {
+    fn from_section(sections: &Sections) -> Result<Self, ValidationError> {
+        let mut ctx = Context::default();
+        if let Some(type_section) = sections.type_section.as_ref() {
+            ctx.types = type_section.entries.clone();
+        }
+        if let Some(import_section) = sections.import_section.as_ref() {
+            for entry in &import_section.entries {
+                match &entry.import_type {
+                    ImportType::Function(index) => {
+                        ctx.func_type(*index)?;
+                        ctx.functions.push(*index);
+                    }
+                    ImportType::Table(t) => {
+                        validate_limits(&t.limits, None)?;
+                        ctx.tables.push(t.elem_type.into());
+                    }
+                    ImportType::Memory(m) => {
+                        validate_limits(&m.limits, Some(MAX_PAGES))?;
+                        ctx.memories += 1;
+                    }
+                    ImportType::Global(g) => {
+                        ctx.globals.push(g.clone());
+                        ctx.imported_globals += 1;
+                    }
+                }
+            }
+        }
+        if let Some(function_section) = sections.function_section.as_ref() {
+            for index in &function_section.types {
+                ctx.func_type(*index)?;
+                ctx.functions.push(*index);
+            }
+        }
+        if let Some(table_section) = sections.table_section.as_ref() {
+            for t in &table_section.entries {
+                validate_limits(&t.limits, None)?;
+                ctx.tables.push(t.elem_type.into());
+            }
+        }
+        if let Some(memory_section) = sections.memory_section.as_ref() {
+            for m in &memory_section.entries {
+                validate_limits(&m.limits, Some(MAX_PAGES))?;
+                ctx.memories += 1;
+            }
+        }
+        // `memory.init` and `data.drop` precede the data section, so they rely
+        // on the data count section instead.
+        ctx.data_segments = match (sections.data_count, sections.data_section.as_ref()) {
+            (Some(count), _) => count as usize,
+            (None, Some(data_section)) => data_section.segments.len(),
+            (None, None) => 0,
+        };
+        if let Some(element_section) = sections.element_section.as_ref() {
+            for segment in &element_section.entries {
+                ctx.elem_segments.push(segment.elem_type.into());
+                ctx.refs
+                    .extend(segment.elems.iter().filter_map(InitExpr::func_index));
+            }
+        }
+        if let Some(global_section) = sections.global_section.as_ref() {
+            let globals = global_section.globals.iter();
+            ctx.refs
+                .extend(globals.filter_map(|g| g.initial_value.func_index()));
+        }
+        if let Some(export_section) = sections.export_section.as_ref() {
+            for entry in &export_section.entries {
+                if entry.kind == ExternalKind::Function {
+                    ctx.refs.insert(entry.index);
+                }
+            }
+        }
+        if ctx.memories > 1 {
+            return Err(ValidationError::MultipleMemoriesError);
+        }
+        Ok(ctx)
+    }
+
+    pub fn func_type(&self, index: u32) -> Result<&FuncType, ValidationError> {
+        self.types
+            .get(index as usize)
+            .ok_or(ValidationError::UnknownTypeError { index })
+    }
+
+    pub fn function(&self, index: u32) -> Result<&FuncType, ValidationError> {
+        let type_index = self
+            .functions
+            .get(index as usize)
+            .ok_or(ValidationError::UnknownFunctionError { index })?;
+        self.func_type(*type_index)
+    }
+
+    pub fn global(&self, index: u32) -> Result<&GlobalType, ValidationError> {
+        self.globals
+            .get(index as usize)
+            .ok_or(ValidationError::UnknownGlobalError { index })
+    }
+
+    /// Returns the element type of the table.
+    pub fn table(&self, index: u32) -> Result<ValueType, ValidationError> {
+        self.tables
+            .get(index as usize)
+            .copied()
+            .ok_or(ValidationError::UnknownTableError { index })
+    }
+
+    pub fn memory(&self, index: u32) -> Result<(), ValidationError> {
+        if index as usize >= self.memories {
+            return Err(ValidationError::UnknownMemoryError { index });
+        }
+        Ok(())
+    }
+
+    pub fn data_segment(&self, index: u32) -> Result<(), ValidationError> {
+        if index as usize >= self.data_segments {
+            return Err(ValidationError::UnknownDataSegmentError { index });
+        }
+        Ok(())
+    }
+
+    /// Returns the element type of the segment.
+    pub fn elem_segment(&self, index: u32) -> Result<ValueType, ValidationError> {
+        self.elem_segments
+            .get(index as usize)
+            .copied()
+            .ok_or(ValidationError::UnknownElemSegmentError { index })
+    }
+
+    pub fn func_ref(&self, index: u32) -> Result<(), ValidationError> {
+        self.function(index)?;
+        if !self.refs.contains(&index) {
+            return Err(ValidationError::UndeclaredFunctionReferenceError);
+        }
+        Ok(())
+    }
+}
+
+pub fn validate(sections: &Sections) -> Result<(), ValidationError> {
+    let mut ctx = Context::from_section(sections)?;
+
+    if let Some(global_section) = sections.global_section.as_ref() {
+        for g in &global_section.globals {
+            validate_const_expr(&ctx, &g.initial_value, g.global_type.value_type)?;
+            ctx.globals.push(g.global_type.clone());
+        }
+    }
+
+    if let Some(export_section) = sections.export_section.as_ref() {
+        let mut names = HashSet::new();
+        for entry in &export_section.entries {
+            if !names.insert(&entry.name) {
+                return Err(ValidationError::DuplicateExportError {
+                    name: entry.name.clone(),
+                });
+            }
+            match entry.kind {
+                ExternalKind::Function => {
+                    ctx.function(entry.index)?;
+                }
+                ExternalKind::Table => {
+                    ctx.table(entry.index)?;
+                }
+                ExternalKind::Memory => ctx.memory(entry.index)?,
+                ExternalKind::Global => {
+                    ctx.global(entry.index)?;
+                }
+            }
+        }
+    }
+
+    if let Some(start_section) = sections.start_section.as_ref() {
+        let func_type = ctx.function(start_section.index)?;
+        if !func_type.args.is_empty() || !func_type.results.is_empty() {
+            return Err(ValidationError::InvalidStartFunctionError);
+        }
+    }
+
+    if let Some(element_section) = sections.element_section.as_ref() {
+        for segment in &element_section.entries {
+            let elem_type = segment.elem_type.into();
+            if let SegmentMode::Active { index, offset } = &segment.mode {
+                if ctx.table(*index)? != elem_type {
+                    return Err(ValidationError::TypeMismatchError);
+                }
+                validate_const_expr(&ctx, offset, ValueType::I32)?;
+            }
+            for elem in &segment.elems {
+                validate_const_expr(&ctx, elem, elem_type)?;
+            }
+        }
+    }
+
+    if let Some(data_section) = sections.data_section.as_ref() {
+        for segment in &data_section.segments {
+            if let SegmentMode::Active { index, offset } = &segment.mode {
+                ctx.memory(*index)?;
+                validate_const_expr(&ctx, offset, ValueType::I32)?;
+            }
+        }
+    }
+
+    let types = sections
+        .function_section
+        .as_ref()
+        .map(|f| f.types.as_slice())
+        .unwrap_or_default();
+    let bodies = sections
+        .code_section
+        .as_ref()
+        .map(|c| c.bodies.as_slice())
+        .unwrap_or_default();
+    for (type_index, body) in types.iter().zip(bodies) {
+        let func_type = ctx.func_type(*type_index)?;
+        FunctionValidator::new(&ctx, func_type, &body.locals).validate(&body.decoded)?;
+    }
+    Ok(())
+}
+
+fn validate_limits(limits: &ResizableLimits, bound: Option<u32>) -> Result<(), ValidationError> {
+    if let Some(bound) = bound {
+        if limits.initial > bound || limits.maximum.unwrap_or(0) > bound {
+            return Err(ValidationError::InvalidMemorySizeError);
+        }
+    }
+    if let Some(maximum) = limits.maximum {
+        if limits.initial > maximum {
+            return Err(ValidationError::InvalidLimitsError);
+        }
+    }
+    Ok(())
+}
+
+fn validate_const_expr(
+    ctx: &Context,
+    expr: &InitExpr,
+    expected: ValueType,
+) -> Result<(), ValidationError> {
+    let value_type = match expr {
+        InitExpr::RuntimeValue(v) => ValueType::from(v.clone()),
+        InitExpr::FuncRef(index) => {
+            ctx.function(*index)?;
+            ValueType::FuncRef
+        }
+        InitExpr::GlobalIndex(index) => {
+            // Only imported globals may be referred by constant expressions.
+            if *index >= ctx.imported_globals {
+                return Err(ValidationError::UnknownGlobalError {
+                    index: *index as u32,
+                });
+            }
+            let global = ctx.global(*index as u32)?;
+            if global.mutability {
+                return Err(ValidationError::ConstantExpressionRequiredError);
+            }
+            global.value_type
+        }
+        InitExpr::Expr(instructions) => {
+            let constant = instructions.iter().all(|(opcode, _)| {
+                matches!(
+                    opcode,
+                    Opcode::I32Const
+                        | Opcode::I64Const
+                        | Opcode::F32Const
+                        | Opcode::F64Const
+                        | Opcode::GetGlobal
+                        | Opcode::RefNull
+                        | Opcode::RefFunc
+                )
+            });
+            if !constant {
+                return Err(ValidationError::ConstantExpressionRequiredError);
+            }
+            // Constant instructions that do not leave exactly one value.
+            return Err(ValidationError::TypeMismatchError);
+        }
+    };
+    if value_type != expected {
+        return Err(ValidationError::TypeMismatchError);
+    }
+    Ok(())
+}
@@ -1,28 +1,29 @@
 use std::rc::Rc;
 
-use super::super::types::*;
-use super::{Instruction, LabelStack};
+use super::ir::Code;
 
+/// A function activation. Its locals live on the shared value stack from `base`,
+/// followed by its operands, so entering a frame does not allocate.
 #[derive(Debug)]
 pub struct StackFrame {
-    pub locals: Vec<RuntimeValue>,
-    pub lstack: LabelStack,
-    pub instructions: Rc<Vec<Instruction>>,
+    pub code: Rc<Code>,
     pub pc: usize,
+    /// Position of the first local on the value stack.
+    pub base: usize,
+    /// Height of the label stack when the frame was entered.
+    pub label_base: usize,
+    /// Number of values the function returns.
+    pub arity: usize,
 }
 
 impl StackFrame {
-    pub fn new(
-        locals: Vec<RuntimeValue>,
-        lstack: LabelStack,
-        instructions: Rc<Vec<Instruction>>,
-        pc: usize,
-    ) -> Self {
+    pub fn new(code: Rc<Code>, pc: usize, base: usize, label_base: usize, arity: usize) -> Self {
         Self {
-            locals,
-            lstack,
-            instructions,
+            code,
             pc,
+            base,
+            label_base,
+            arity,
         }
     }
 }
@@ -48,4 +49,9 @@ impl CallStack {
     pub fn len(&self) -> usize {
         self.buf.len()
     }
+
+    /// Frames from the bottom of the stack to the top.
+    pub fn frames(&self) -> std::slice::Iter<'_, StackFrame> {
+        self.buf.iter()
+    }
 }
@@ -2,6 +2,8 @@ use std::io;
 
 use crate::decoder::DecodeError;
 
+use super::trap::{Trap, TrapCode};
+
 #[derive(Debug, Fail)]
 pub enum RuntimeError {
     #[fail(display = "Uncaught TypeError.")]
@@ -16,24 +18,6 @@ pub enum RuntimeError {
     #[fail(display = "Type Mismatch Operation Error.")]
     TypeMismatchOperationError,
 
-    #[fail(display = "out of bounds memory access")]
-    OutOfBoundsMemoryAccessError,
-
-    #[fail(display = "integer divide by zero")]
-    DivisionByZeroError,
-
-    #[fail(display = "integer overflow")]
-    DivisionOverflowError,
-
-    #[fail(display = "integer overflow")]
-    IntegerOverflowError,
-
-    #[fail(display = "indirect call type mismatch")]
-    IndirectCallTypeMismatchError,
-
-    #[fail(display = "invalid conversion to integer")]
-    UnrepresentableIntegerError,
-
     #[fail(display = "undefinedMemoryError: please define and load memory")]
     UndefinedMemoryError,
 
@@ -49,11 +33,8 @@ pub enum RuntimeError {
     #[fail(display = "undefinedExportError: please define export")]
     UndefinedExportError,
 
-    #[fail(display = "undefined element")]
-    UndefinedElementError,
-
-    #[fail(display = "uninitialized element")]
-    UnInitializedElementError,
+    #[fail(display = "{}", trap)]
+    Trap { trap: Trap },
 
     #[fail(display = "Some I/O Error: {:?}", error)]
     IOError { error: io::Error },
@@ -82,3 +63,17 @@ impl From<std::str::Utf8Error> for RuntimeError {
         RuntimeError::Utf8Error { error }
     }
 }
+
+impl From<Trap> for RuntimeError {
+    fn from(trap: Trap) -> Self {
+        RuntimeError::Trap { trap }
+    }
+}
+
+impl From<TrapCode> for RuntimeError {
+    fn from(code: TrapCode) -> Self {
+        RuntimeError::Trap {
+            trap: Trap::new(code),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::cell::Cell;
+
+use super::instructions::Opcode;
+
+/// One slot per single byte opcode followed by one per `0xFC` prefixed opcode.
+const SLOTS: usize = 0x200;
+
+/// Fuel consumed by each executed instruction.
+/// Opcodes without an explicit entry cost `default_cost`.
+#[derive(Debug, Clone)]
+pub struct CostTable {
+    costs: Box<[u64; SLOTS]>,
+}
+
+impl Default for CostTable {
+    fn default() -> Self {
+        CostTable::new(1)
+    }
+}
+
+impl CostTable {
+    pub fn new(default_cost: u64) -> Self {
+        CostTable {
+            costs: Box::new([default_cost; SLOTS]),
+        }
+    }
+
+    pub fn set(&mut self, opcode: Opcode, cost: u64) {
+        self.costs[slot(opcode)] = cost;
+    }
+
+    #[inline]
+    pub fn cost(&self, opcode: Opcode) -> u64 {
+        self.costs[slot(opcode)]
+    }
+}
+
+#[inline]
+fn slot(opcode: Opcode) -> usize {
+    let opcode = opcode as usize;
+    if opcode > 0xFF {
+        0x100 | (opcode & 0xFF)
+    } else {
+        opcode
+    }
+}
+
+/// Remaining fuel of a VM. Metering is disabled until a budget is set.
+#[derive(Debug, Default)]
+pub(crate) struct Fuel {
+    remaining: Cell<Option<u64>>,
+    costs: CostTable,
+}
+
+impl Fuel {
+    pub fn set(&self, fuel: u64) {
+        self.remaining.set(Some(fuel));
+    }
+
+    pub fn add(&self, fuel: u64) {
+        let remaining = self.remaining.get().unwrap_or(0);
+        self.remaining.set(Some(remaining.saturating_add(fuel)));
+    }
+
+    pub fn remaining(&self) -> Option<u64> {
+        self.remaining.get()
+    }
+
+    pub fn set_cost_table(&mut self, costs: CostTable) {
+        self.costs = costs;
+    }
+
+    /// Consumes the fuel for `opcode`, returning `false` if there is not enough left.
+    pub fn consume(&self, opcode: Opcode) -> bool {
+        if let Some(remaining) = self.remaining.get() {
+            let cost = self.costs.cost(opcode);
+            if remaining < cost {
+                return false;
+            }
+            self.remaining.set(Some(remaining - cost));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn consume_fuel_by_cost_table() {
+        let mut fuel = Fuel::default();
+        let mut costs = CostTable::default();
+        costs.set(Opcode::Call, 5);
+        costs.set(Opcode::MemoryFill, 7);
+        assert_eq!(costs.cost(Opcode::MemoryFill), 7);
+        assert_eq!(costs.cost(Opcode::MemoryCopy), 1);
+        assert_eq!(costs.cost(Opcode::CallIndirect), 1);
+        fuel.set_cost_table(costs);
+        assert!(fuel.consume(Opcode::Call));
+        fuel.set(6);
+        assert!(fuel.consume(Opcode::Call));
+        assert!(fuel.consume(Opcode::I32Add));
+        assert_eq!(fuel.remaining(), Some(0));
+        assert!(!fuel.consume(Opcode::I32Add));
+        fuel.add(1);
+        assert!(fuel.consume(Opcode::I32Add));
+    }
+}
@@ -3,7 +3,8 @@ use crate::types::*;
 
 use std::rc::Rc;
 
-use super::{ImportType, Instruction, RuntimeError, Sections};
+use super::ir::Code;
+use super::{ImportType, RuntimeError, Sections};
 
 #[derive(Debug)]
 pub enum FunctionInstance {
@@ -16,9 +17,9 @@ pub type FunctionInstanceRef = Rc<FunctionInstance>;
 #[derive(Debug, Clone)]
 pub struct InternalFunction {
     pub args: Vec<ValueType>,
-    pub instructions: Rc<Vec<Instruction>>,
+    pub code: Rc<Code>,
     pub locals: Vec<LocalEntry>,
-    pub results: Vec<ResultType>,
+    pub results: Vec<ValueType>,
 }
 
 #[derive(Debug)]
@@ -26,14 +27,17 @@ pub struct ExternalFunction {
     pub module_name: String,
     pub field_name: String,
     pub args: Vec<ValueType>,
-    pub results: Vec<ResultType>,
+    pub results: Vec<ValueType>,
 }
 
 #[derive(Debug)]
 pub struct Functions(Vec<FunctionInstanceRef>);
 
 impl Functions {
-    pub fn from_section(sections: &Sections) -> Result<Self, RuntimeError> {
+    pub fn from_section(
+        sections: &Sections,
+        compiled: &[InternalFunction],
+    ) -> Result<Self, RuntimeError> {
         let mut functions = vec![];
         if let Some(import_section) = sections.import_section.as_ref() {
             for entry in &import_section.entries {
@@ -56,35 +60,8 @@ impl Functions {
             }
         }
 
-        if let Some(code) = sections.code_section.as_ref() {
-            let types = {
-                if let Some(func_section) = sections.function_section.as_ref() {
-                    func_section.types.clone()
-                } else {
-                    vec![]
-                }
-            };
-            let type_section = sections
-                .type_section
-                .as_ref()
-                .expect("should has type section");
-            for (i, b) in code.bodies.iter().enumerate() {
-                let instructions = Rc::new(b.decoded.clone());
-                let locals = b.locals.clone();
-                // Find type index from func section types
-                // Then Find arg signature from type section entries.
-                let type_index = types[i];
-                let args = type_section.entries[type_index as usize].args.clone();
-                let results = type_section.entries[type_index as usize].results.clone();
-                functions.push(Rc::new(FunctionInstance::InternalFunction(
-                    InternalFunction {
-                        instructions,
-                        locals,
-                        args,
-                        results,
-                    },
-                )));
-            }
+        for func in compiled {
+            functions.push(Rc::new(FunctionInstance::InternalFunction(func.clone())));
         }
 
         Ok(Self(functions))
@@ -4,9 +4,9 @@ use std::rc::Rc;
 use super::runtime_value::*;
 use super::value_type::*;
 
-use super::{ImportResolver, ImportType, RuntimeError, Sections};
+use super::{Functions, ImportResolver, ImportType, RuntimeError, Sections};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Global {
     pub mutability: bool,
     pub value: RuntimeValue,
@@ -31,16 +31,17 @@ pub struct Globals(Vec<GlobalRef>);
 impl Globals {
     pub fn from_section<'a>(
         sections: &Sections,
+        functions: &Functions,
         imports: Option<&'a dyn ImportResolver>,
     ) -> Result<Self, RuntimeError> {
-        let mut globals = vec![];
+        let mut globals = Self(vec![]);
         if let Some(import_section) = sections.import_section.as_ref() {
             if let Some(imports) = &imports {
                 for entry in &import_section.entries {
                     if let ImportType::Global(_) = entry.import_type {
                         let global = imports
                             .resolve_global(entry.module_name.clone(), entry.field_name.clone())?;
-                        globals.push(global);
+                        globals.0.push(global);
                     }
                 }
             }
@@ -48,14 +49,16 @@ impl Globals {
 
         if let Some(global) = sections.global_section.as_ref() {
             for g in &global.globals {
-                globals.push(Rc::new(RefCell::new(Global::new(
+                // Initializers only refer to imported globals, which come first.
+                let value = g.initial_value.eval(&globals, functions)?;
+                globals.0.push(Rc::new(RefCell::new(Global::new(
                     g.global_type.mutability,
-                    g.initial_value,
+                    value,
                     g.global_type.value_type,
                 ))));
             }
         };
-        Ok(Self(globals))
+        Ok(globals)
     }
 
     pub fn get(&self, index: usize) -> Option<&GlobalRef> {
@@ -0,0 +1,22 @@
+#include <setjmp.h>
+
+/*
+ * Runs `body(payload)` with a jump buffer published in `*slot`, so that the
+ * fault handler can unwind back here. Returns 0 if it did, 1 otherwise.
+ */
+int yaw_catch_traps(void **slot, void (*body)(void *), void *payload) {
+    sigjmp_buf buf;
+    void *prev = *slot;
+    if (sigsetjmp(buf, 0) != 0) {
+        *slot = prev;
+        return 0;
+    }
+    *slot = &buf;
+    body(payload);
+    *slot = prev;
+    return 1;
+}
+
+void yaw_unwind(void *buf) {
+    siglongjmp(*(sigjmp_buf *)buf, 1);
+}
@@ -0,0 +1,231 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Deref, DerefMut, Range};
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::slice;
+use std::sync::OnceLock;
+
+use super::{RuntimeError, TrapCode};
+
+/// The largest memory the spec allows, 65536 pages.
+pub const ACCESSIBLE_SIZE: usize = 1 << 32;
+/// An effective address is a `u32` base plus a `u32` offset, so with this much
+/// address space reserved every access of a guest lands inside the reservation.
+const RESERVED_SIZE: usize = (1 << 33) + 0x10000;
+
+extern "C-unwind" {
+    fn yaw_catch_traps(
+        slot: *mut *mut c_void,
+        body: unsafe extern "C-unwind" fn(*mut c_void),
+        payload: *mut c_void,
+    ) -> c_int;
+}
+
+extern "C" {
+    fn yaw_unwind(buf: *mut c_void) -> !;
+}
+
+thread_local! {
+    /// Jump buffer of the innermost `catch_traps` on this thread.
+    static JMP_BUF: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+    /// Address ranges reserved by the guarded buffers of this thread.
+    static REGIONS: RefCell<Vec<Range<usize>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether installing the fault handler failed, and with which error number.
+static INSTALL_HANDLER: OnceLock<Result<(), i32>> = OnceLock::new();
+static mut PREV_SIGSEGV: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+static mut PREV_SIGBUS: MaybeUninit<libc::sigaction> = MaybeUninit::uninit();
+
+/// Runs the raw guest access `f`, turning a fault on the guard pages of a
+/// linear memory into an out of bounds memory access trap.
+/// A fault jumps straight back here, so `f` and its result are `Copy`: nothing
+/// between this frame and the faulting access has a destructor to skip.
+pub(crate) fn catch_traps<R: Copy, F: FnOnce() -> R + Copy>(f: F) -> Result<R, RuntimeError> {
+    unsafe extern "C-unwind" fn call<R: Copy, F: FnOnce() -> R + Copy>(payload: *mut c_void) {
+        let (f, result) = &mut *(payload as *mut (F, Option<R>));
+        *result = Some(f());
+    }
+
+    let mut payload = (f, None);
+    let slot = JMP_BUF.with(|b| b.as_ptr());
+    let body = call::<R, F> as unsafe extern "C-unwind" fn(*mut c_void);
+    let completed = unsafe {
+        yaw_catch_traps(
+            slot,
+            body,
+            &mut payload as *mut (_, Option<R>) as *mut c_void,
+        )
+    };
+    match payload.1 {
+        Some(result) if completed != 0 => Ok(result),
+        _ => Err(TrapCode::OutOfBoundsMemoryAccess.into()),
+    }
+}
+
+/// The buffer of a linear memory, placed at the start of a reservation big
+/// enough that any effective address falls inside it. Pages past the current
+/// length stay inaccessible, and growing only changes their protection, so
+/// the buffer never moves.
+pub struct GuardedBuffer {
+    base: *mut u8,
+    len: usize,
+}
+
+impl GuardedBuffer {
+    /// Fails if the handler cannot be installed, the address space cannot be
+    /// reserved or the first `len` bytes cannot be committed.
+    pub fn new(len: usize) -> Result<GuardedBuffer, RuntimeError> {
+        if let Err(errno) = INSTALL_HANDLER.get_or_init(|| unsafe { install_handler() }) {
+            return Err(io::Error::from_raw_os_error(*errno).into());
+        }
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                RESERVED_SIZE,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        let base = base as *mut u8;
+        REGIONS.with(|r| {
+            let start = base as usize;
+            r.borrow_mut().push(start..start + RESERVED_SIZE)
+        });
+        let mut buf = GuardedBuffer { base, len: 0 };
+        buf.resize(len)?;
+        Ok(buf)
+    }
+
+    /// Grows the buffer to `new_len` bytes by committing zeroed pages. On
+    /// failure the buffer keeps its length.
+    pub fn resize(&mut self, new_len: usize) -> io::Result<()> {
+        assert!(self.len <= new_len && new_len <= ACCESSIBLE_SIZE);
+        if new_len == self.len {
+            return Ok(());
+        }
+        let res = unsafe {
+            libc::mprotect(
+                self.base.add(self.len) as *mut c_void,
+                new_len - self.len,
+                libc::PROT_READ | libc::PROT_WRITE,
+            )
+        };
+        if res != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.len = new_len;
+        Ok(())
+    }
+}
+
+impl Deref for GuardedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.base, self.len) }
+    }
+}
+
+impl DerefMut for GuardedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.base, self.len) }
+    }
+}
+
+impl Drop for GuardedBuffer {
+    fn drop(&mut self) {
+        let start = self.base as usize;
+        let _ = REGIONS.try_with(|r| r.borrow_mut().retain(|region| region.start != start));
+        unsafe {
+            libc::munmap(self.base as *mut c_void, RESERVED_SIZE);
+        }
+    }
+}
+
+unsafe fn install_handler() -> Result<(), i32> {
+    let mut action: libc::sigaction = mem::zeroed();
+    action.sa_sigaction = handle_fault as *const () as usize;
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_NODEFER;
+    libc::sigemptyset(&mut action.sa_mask);
+    if libc::sigaction(
+        libc::SIGSEGV,
+        &action,
+        (*ptr::addr_of_mut!(PREV_SIGSEGV)).as_mut_ptr(),
+    ) != 0
+        || libc::sigaction(
+            libc::SIGBUS,
+            &action,
+            (*ptr::addr_of_mut!(PREV_SIGBUS)).as_mut_ptr(),
+        ) != 0
+    {
+        return Err(io::Error::last_os_error().raw_os_error().unwrap_or(0));
+    }
+    Ok(())
+}
+
+fn is_guarded(addr: usize) -> bool {
+    REGIONS
+        .try_with(|r| match r.try_borrow() {
+            Ok(regions) => regions.iter().any(|region| region.contains(&addr)),
+            Err(_) => false,
+        })
+        .unwrap_or(false)
+}
+
+unsafe extern "C" fn handle_fault(signum: c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+    if is_guarded((*info).si_addr() as usize) {
+        let buf = JMP_BUF.try_with(|b| b.get()).unwrap_or(ptr::null_mut());
+        if !buf.is_null() {
+            yaw_unwind(buf);
+        }
+    }
+
+    // Not a guest access, hand the fault to whoever handled it before us.
+    let prev = if signum == libc::SIGBUS {
+        (*ptr::addr_of!(PREV_SIGBUS)).as_ptr()
+    } else {
+        (*ptr::addr_of!(PREV_SIGSEGV)).as_ptr()
+    };
+    let handler = (*prev).sa_sigaction;
+    if handler == libc::SIG_DFL || handler == libc::SIG_IGN {
+        // Restore the previous disposition and let the faulting access run again.
+        libc::sigaction(signum, prev, ptr::null_mut());
+    } else if (*prev).sa_flags & libc::SA_SIGINFO != 0 {
+        let handler: extern "C" fn(c_int, *mut libc::siginfo_t, *mut c_void) =
+            mem::transmute(handler);
+        handler(signum, info, context);
+    } else {
+        let handler: extern "C" fn(c_int) = mem::transmute(handler);
+        handler(signum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::vm::{MemoryDescriptor, MemoryRef, PAGE_SIZE};
+
+    #[test]
+    fn trap_on_guard_page() {
+        let memory = MemoryRef::new(MemoryDescriptor::new(1, None)).unwrap();
+        let trapped = |addr: u64| match memory.guest_load::<i32>(addr) {
+            Ok(_) => false,
+            Err(RuntimeError::Trap { trap }) => trap.code() == TrapCode::OutOfBoundsMemoryAccess,
+            Err(e) => panic!("unexpected error {:?}", e),
+        };
+        assert!(!trapped(PAGE_SIZE as u64 - 4));
+        assert!(trapped(PAGE_SIZE as u64 - 2));
+        assert!(trapped(u64::from(u32::MAX) * 2));
+        assert_eq!(memory.grow(1), 1);
+        assert!(!trapped(PAGE_SIZE as u64 - 2));
+    }
+}
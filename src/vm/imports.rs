@@ -46,6 +46,12 @@ pub trait FunctionResolver: fmt::Debug {
     ) -> Result<Vec<RuntimeValue>, RuntimeError> {
         Err(RuntimeError::UndefinedFunctionError)
     }
+
+    /// Checked right after `invoke` returns. Returning `true` suspends a resumable
+    /// invocation after the call, so that the embedder can resume it later.
+    fn yield_requested(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Clone, Default)]
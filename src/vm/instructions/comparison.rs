@@ -4,32 +4,65 @@ use crate::vm::value_stack::ValueStack;
 
 use super::pop::*;
 
-macro_rules! compare {
-    ($name: ident) => {
+macro_rules! eqz {
+    ($name: ident, $t: ty) => {
         pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
-            let (left, right) = pop_lr(stack)?;
-            stack.push(left.$name(&right)?);
+            let v: $t = pop_as(stack)?;
+            stack.push(RuntimeValue::I32((v == 0) as i32));
             Ok(())
         }
     };
 }
 
-pub fn eqz(stack: &mut ValueStack) -> Result<(), RuntimeError> {
-    let res = pop(stack)?.is_zero() as i32;
-    stack.push(RuntimeValue::I32(res));
-    Ok(())
+macro_rules! compare {
+    ($name: ident, $t: ty, |$l: ident, $r: ident| $body: expr) => {
+        pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
+            let $r: $t = pop_as(stack)?;
+            let $l: $t = pop_as(stack)?;
+            stack.push(RuntimeValue::I32($body as i32));
+            Ok(())
+        }
+    };
 }
 
-compare!(eq);
-compare!(ne);
-compare!(lt_s);
-compare!(lt_u);
-compare!(ge_s);
-compare!(gt_s);
-compare!(gt_u);
-compare!(le_s);
-compare!(le_u);
-compare!(ge_u);
+eqz!(i32_eqz, i32);
+eqz!(i64_eqz, i64);
+
+compare!(i32_eq, i32, |l, r| l == r);
+compare!(i32_ne, i32, |l, r| l != r);
+compare!(i32_lt_s, i32, |l, r| l < r);
+compare!(i32_lt_u, i32, |l, r| (l as u32) < (r as u32));
+compare!(i32_gt_s, i32, |l, r| l > r);
+compare!(i32_gt_u, i32, |l, r| (l as u32) > (r as u32));
+compare!(i32_le_s, i32, |l, r| l <= r);
+compare!(i32_le_u, i32, |l, r| (l as u32) <= (r as u32));
+compare!(i32_ge_s, i32, |l, r| l >= r);
+compare!(i32_ge_u, i32, |l, r| (l as u32) >= (r as u32));
+
+compare!(i64_eq, i64, |l, r| l == r);
+compare!(i64_ne, i64, |l, r| l != r);
+compare!(i64_lt_s, i64, |l, r| l < r);
+compare!(i64_lt_u, i64, |l, r| (l as u64) < (r as u64));
+compare!(i64_gt_s, i64, |l, r| l > r);
+compare!(i64_gt_u, i64, |l, r| (l as u64) > (r as u64));
+compare!(i64_le_s, i64, |l, r| l <= r);
+compare!(i64_le_u, i64, |l, r| (l as u64) <= (r as u64));
+compare!(i64_ge_s, i64, |l, r| l >= r);
+compare!(i64_ge_u, i64, |l, r| (l as u64) >= (r as u64));
+
+compare!(f32_eq, f32, |l, r| l == r);
+compare!(f32_ne, f32, |l, r| l != r);
+compare!(f32_lt, f32, |l, r| l < r);
+compare!(f32_gt, f32, |l, r| l > r);
+compare!(f32_le, f32, |l, r| l <= r);
+compare!(f32_ge, f32, |l, r| l >= r);
+
+compare!(f64_eq, f64, |l, r| l == r);
+compare!(f64_ne, f64, |l, r| l != r);
+compare!(f64_lt, f64, |l, r| l < r);
+compare!(f64_gt, f64, |l, r| l > r);
+compare!(f64_le, f64, |l, r| l <= r);
+compare!(f64_ge, f64, |l, r| l >= r);
 
 #[cfg(test)]
 mod tests {
@@ -39,7 +72,7 @@ mod tests {
     fn eqz_should_push_0() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x01));
-        eqz(&mut stack)?;
+        i32_eqz(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)]);
         Ok(())
     }
@@ -48,7 +81,7 @@ mod tests {
     fn eqz_should_push_1() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I64(0x00));
-        eqz(&mut stack)?;
+        i64_eqz(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)]);
         Ok(())
     }
@@ -58,7 +91,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x01));
         stack.push(RuntimeValue::I32(0x01));
-        eq(&mut stack)?;
+        i32_eq(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)]);
         Ok(())
     }
@@ -68,7 +101,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(1.23));
         stack.push(RuntimeValue::F32(1.23));
-        eq(&mut stack)?;
+        f32_eq(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)]);
         Ok(())
     }
@@ -78,7 +111,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(1.23));
         stack.push(RuntimeValue::F64(1.22));
-        eq(&mut stack)?;
+        f64_eq(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)]);
         Ok(())
     }
@@ -88,7 +121,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x01));
         stack.push(RuntimeValue::I32(0x00));
-        ne(&mut stack)?;
+        i32_ne(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)]);
         Ok(())
     }
@@ -98,7 +131,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(0.01));
         stack.push(RuntimeValue::F64(0.01));
-        ne(&mut stack)?;
+        f64_ne(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)]);
         Ok(())
     }
@@ -108,7 +141,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        ge_s(&mut stack)?;
+        i32_ge_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)],);
         Ok(())
     }
@@ -118,7 +151,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        lt_s(&mut stack)?;
+        i32_lt_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)],);
         Ok(())
     }
@@ -128,7 +161,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(1));
         stack.push(RuntimeValue::I32(-1));
-        lt_u(&mut stack)?;
+        i32_lt_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)],);
         Ok(())
     }
@@ -138,7 +171,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I64(-1));
         stack.push(RuntimeValue::I64(1));
-        lt_u(&mut stack)?;
+        i64_lt_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)],);
         Ok(())
     }
@@ -148,7 +181,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        gt_s(&mut stack)?;
+        i32_gt_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)],);
         Ok(())
     }
@@ -158,7 +191,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        gt_u(&mut stack)?;
+        i32_gt_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)],);
         Ok(())
     }
@@ -168,7 +201,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        le_s(&mut stack)?;
+        i32_le_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)],);
         Ok(())
     }
@@ -178,7 +211,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(2));
         stack.push(RuntimeValue::I32(-2));
-        ge_u(&mut stack)?;
+        i32_ge_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0)],);
         Ok(())
     }
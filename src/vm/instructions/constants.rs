@@ -2,27 +2,23 @@ use crate::error::YawError;
 use crate::types::*;
 use crate::vm::value_stack::ValueStack;
 
-pub fn i32_const(operands: &[Operand], stack: &mut ValueStack) -> Result<(), YawError> {
-    let v: RuntimeValue = operands[0].into();
-    stack.push(v);
+pub fn i32_const(v: i32, stack: &mut ValueStack) -> Result<(), YawError> {
+    stack.push(RuntimeValue::I32(v));
     Ok(())
 }
 
-pub fn i64_const(operands: &[Operand], stack: &mut ValueStack) -> Result<(), YawError> {
-    let v: RuntimeValue = operands[0].into();
-    stack.push(v);
+pub fn i64_const(v: i64, stack: &mut ValueStack) -> Result<(), YawError> {
+    stack.push(RuntimeValue::I64(v));
     Ok(())
 }
 
-pub fn f32_const(operands: &[Operand], stack: &mut ValueStack) -> Result<(), YawError> {
-    let v: RuntimeValue = operands[0].into();
-    stack.push(v);
+pub fn f32_const(v: f32, stack: &mut ValueStack) -> Result<(), YawError> {
+    stack.push(RuntimeValue::F32(v));
     Ok(())
 }
 
-pub fn f64_const(operands: &[Operand], stack: &mut ValueStack) -> Result<(), YawError> {
-    let v: RuntimeValue = operands[0].into();
-    stack.push(v);
+pub fn f64_const(v: f64, stack: &mut ValueStack) -> Result<(), YawError> {
+    stack.push(RuntimeValue::F64(v));
     Ok(())
 }
 
@@ -33,7 +29,7 @@ mod tests {
     #[test]
     fn test_i64const() -> Result<(), YawError> {
         let mut stack = ValueStack::new();
-        i64_const(&[Operand::I64(42)], &mut stack)?;
+        i64_const(42, &mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I64(42)]);
         Ok(())
     }
@@ -41,7 +37,7 @@ mod tests {
     #[test]
     fn test_f64const() -> Result<(), YawError> {
         let mut stack = ValueStack::new();
-        f64_const(&[Operand::F64(42.0)], &mut stack)?;
+        f64_const(42.0, &mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(42.0)]);
         Ok(())
     }
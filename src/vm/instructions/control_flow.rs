@@ -1,231 +1,129 @@
 use crate::vm::error::*;
 use crate::vm::value_stack::ValueStack;
 
-use crate::decoder::*;
-use crate::types::*;
 use crate::vm::instructions::*;
 use crate::vm::label::*;
 
 pub fn br_table(
-    operands: &[Operand],
-    instrs: &[Instruction],
-    mut pc: usize,
+    depths: &[u32],
+    end: usize,
+    label_base: usize,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<usize, RuntimeError> {
-    let index: usize = pop(vstack)?.into();
-    let count: u32 = operands[0].into();
-    let count = count as usize;
-    let indexes = {
-        let mut indexes = vec![];
-        for i in 0..count {
-            let index: usize = operands[i + 1].into();
-            indexes.push(index);
-        }
-        indexes
-    };
-    let default_index: usize = operands[count + 1].into();
-    let before = if let Some(i) = indexes.get(index) {
-        i + 1
-    } else {
-        default_index + 1
-    };
-    let should_exit = lstack.len() < before as usize;
-    let labels = lstack.take_before(before as u32);
-    let label = labels.get(0).expect("should take label");
-    match label.block_type {
-        BlockType::Block | BlockType::If => {
-            pc = skip_until_end(instrs, pc, before)?;
-            if label.result_type == ResultType::Empty {
-                while vstack.len() > label.sp {
-                    vstack.pop();
-                }
-            } else {
-                let first = vstack.pop().expect("should pop");
-                while vstack.len() > label.sp {
-                    vstack.pop();
-                }
-                vstack.push(first);
-            }
-        }
-        BlockType::Loop => pc = do_loop(instrs, label, should_exit)?,
-    }
-    Ok(pc)
+    let index = pop_u32(vstack)? as usize;
+    // The default depth is the last entry.
+    let depth = depths.get(index).unwrap_or(&depths[depths.len() - 1]);
+    branch(*depth as usize, end, label_base, vstack, lstack)
 }
 
 pub fn br(
-    operands: &[Operand],
-    instrs: &[Instruction],
-    mut pc: usize,
+    depth: u32,
+    end: usize,
+    label_base: usize,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<usize, RuntimeError> {
-    let depth: usize = operands[0].into();
-    let before = depth + 1;
-    let should_exit = lstack.len() < before;
-    let labels = lstack.take_before(before as u32);
-    let label = labels.get(0).expect("should take label");
-    match label.block_type {
-        BlockType::Block | BlockType::If => {
-            pc = skip_until_end(instrs, pc, depth as usize + 1)?;
-            if label.result_type == ResultType::Empty {
-                while vstack.len() > label.sp {
-                    vstack.pop();
-                }
-            } else {
-                let first = vstack.pop().expect("should pop");
-                while vstack.len() > label.sp {
-                    vstack.pop();
-                }
-                vstack.push(first);
-            }
-        }
-        BlockType::Loop => pc = do_loop(instrs, label, should_exit)?,
-    }
-    Ok(pc)
+    branch(depth as usize, end, label_base, vstack, lstack)
 }
 
 pub fn br_if(
-    operands: &[Operand],
-    instrs: &[Instruction],
-    mut pc: usize,
+    depth: u32,
+    pc: usize,
+    end: usize,
+    label_base: usize,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<usize, RuntimeError> {
-    let depth: usize = operands[0].into();
-    let opland = pop(vstack)?;
-    if !opland.is_zero() {
-        let before = depth + 1;
-        let should_exit = lstack.len() < before as usize;
-        let labels = lstack.take_before(before as u32);
-        let label = labels.get(0).expect("should take label");
-        match label.block_type {
-            BlockType::Block | BlockType::If => {
-                pc = skip_until_end(instrs, pc, depth as usize + 1)?;
-                if label.result_type == ResultType::Empty {
-                    while vstack.len() > label.sp {
-                        vstack.pop();
-                    }
-                } else {
-                    let first = vstack.pop().expect("should pop");
-                    while vstack.len() > label.sp {
-                        vstack.pop();
-                    }
-                    vstack.push(first);
-                }
-            }
-            BlockType::Loop => pc = do_loop(instrs, label, should_exit)?,
-        }
+    let condition: i32 = pop_as(vstack)?;
+    if condition == 0 {
+        return Ok(pc);
     }
-    Ok(pc)
+    branch(depth as usize, end, label_base, vstack, lstack)
 }
 
-fn do_loop(
-    instrs: &[Instruction],
-    label: &Label,
-    should_exit: bool,
+/// Unwinds the labels up to `depth` and returns the continuation of the target.
+/// `end` is the end of the function body and `label_base` the first label of the frame.
+fn branch(
+    depth: usize,
+    end: usize,
+    label_base: usize,
+    vstack: &mut ValueStack,
+    lstack: &mut LabelStack,
 ) -> Result<usize, RuntimeError> {
-    let pos = label.position;
-    let mut pc = pos as usize;
-    // Please see. br_if.wast's as-loop_last
-    // I guess we need to skip to end in this case.
-    //
+    // A branch beyond the outermost label targets the function body itself,
+    // so it behaves like `return`.
     // (func (export "as-loop-last") (param i32)
     //   (loop (call $dummy) (br_if 1 (local.get 0)))
     // )
-    if should_exit {
-        pc = skip_until_end(instrs, pc, 0)?;
+    if lstack.len() - label_base <= depth {
+        lstack.truncate(label_base);
+        return Ok(end);
     }
-    Ok(pc)
+    let label = *lstack.get(depth).ok_or(RuntimeError::LabelError)?;
+    lstack.truncate(lstack.len() - depth - 1);
+    vstack
+        .drop_keep(label.sp, label.arity)
+        .ok_or(RuntimeError::StackPopError)?;
+    Ok(label.continuation)
+}
+
+/// Height of the value stack below the `params` values a block takes.
+fn block_sp(params: u32, vstack: &ValueStack) -> Result<usize, RuntimeError> {
+    vstack
+        .len()
+        .checked_sub(params as usize)
+        .ok_or(RuntimeError::StackPopError)
 }
 
 pub fn block(
-    operands: &[Operand],
-    pc: usize,
+    params: u32,
+    results: u32,
+    end: u32,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<(), RuntimeError> {
-    if let Operand::ResultType(rtype) = operands[0] {
-        lstack.push(Label::new(pc - 1, BlockType::Block, rtype, vstack.len()));
-    }
+    let sp = block_sp(params, vstack)?;
+    let label = Label::new(end as usize, results as usize, sp);
+    lstack.push(label);
     Ok(())
 }
 
 pub fn r#loop(
-    operands: &[Operand],
+    params: u32,
     pc: usize,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<(), RuntimeError> {
-    if let Operand::ResultType(rtype) = operands[0] {
-        lstack.push(Label::new(pc - 1, BlockType::Loop, rtype, vstack.len()));
-    }
+    let sp = block_sp(params, vstack)?;
+    lstack.push(Label::new(pc - 1, params as usize, sp));
     Ok(())
 }
 
 pub fn r#if(
-    operands: &[Operand],
-    instrs: &[Instruction],
-    mut pc: usize,
+    params: u32,
+    results: u32,
+    els: u32,
+    end: u32,
+    pc: usize,
     vstack: &mut ValueStack,
     lstack: &mut LabelStack,
 ) -> Result<usize, RuntimeError> {
-    if let Operand::ResultType(rtype) = operands[0] {
-        lstack.push(Label::new(pc - 1, BlockType::If, rtype, vstack.len()));
-        if vstack.pop().expect("should pop").is_zero() {
-            pc = skip_until_else_or_end(instrs, pc)?;
-            if instrs[pc - 1].0 == Opcode::End {
-                // POP If label when end.
-                lstack.pop();
-            }
+    let condition: i32 = pop_as(vstack)?;
+    let sp = block_sp(params, vstack)?;
+    let label = Label::new(end as usize, results as usize, sp);
+    lstack.push(label);
+    if condition == 0 {
+        if els == end {
+            // POP If label when end.
+            lstack.pop();
         }
+        return Ok(els as usize);
     }
     Ok(pc)
 }
 
-pub fn r#else(
-    instrs: &[Instruction],
-    mut pc: usize,
-    lstack: &mut LabelStack,
-) -> Result<usize, RuntimeError> {
-    pc = skip_until_end(instrs, pc, 1)?;
-    lstack.pop();
-    Ok(pc)
-}
-
-fn skip_until_else_or_end(instr: &[Instruction], mut pc: usize) -> Result<usize, RuntimeError> {
-    let mut block_depth = 1;
-    loop {
-        let (op, _) = instr[pc];
-        pc += 1;
-        match op {
-            Opcode::End => block_depth -= 1,
-            Opcode::If | Opcode::Block | Opcode::Loop => {
-                block_depth += 1;
-            }
-            _ => (),
-        };
-        if block_depth == 0 || (op == Opcode::Else && block_depth == 1) {
-            return Ok(pc);
-        }
-    }
-}
-
-fn skip_until_end(
-    instr: &[Instruction],
-    mut pc: usize,
-    mut block_depth: usize,
-) -> Result<usize, RuntimeError> {
-    loop {
-        let (op, _) = instr[pc];
-        pc += 1;
-        match op {
-            Opcode::End => block_depth -= 1,
-            Opcode::If | Opcode::Block | Opcode::Loop => block_depth += 1,
-            _ => (),
-        };
-        if block_depth == 0 {
-            return Ok(pc);
-        }
-    }
+pub fn r#else(lstack: &mut LabelStack) -> Result<usize, RuntimeError> {
+    let label = lstack.pop().ok_or(RuntimeError::LabelError)?;
+    Ok(label.continuation)
 }
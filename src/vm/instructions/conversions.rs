@@ -1,42 +1,71 @@
+use crate::types::{Convert, RuntimeValue, Trunc, TruncSat};
 use crate::vm::error::*;
 use crate::vm::pop::*;
 use crate::vm::value_stack::ValueStack;
 
-macro_rules! unary {
-    ($name: ident) => {
+macro_rules! convert {
+    ($name: ident, $from: ty => $to: ty, |$v: ident| $body: expr) => {
         pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
-            let v = pop(stack)?;
-            stack.push(v.$name()?);
+            let $v: $from = pop_as(stack)?;
+            let result: $to = $body;
+            stack.push(RuntimeValue::from(result));
+            Ok(())
+        }
+    };
+    ($name: ident, $from: ty, |$v: ident| $body: expr) => {
+        pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
+            let $v: $from = pop_as(stack)?;
+            stack.push(RuntimeValue::from($body));
             Ok(())
         }
     };
 }
 
-unary!(wrap);
-unary!(trunc_s_toi32);
-unary!(trunc_u_toi32);
-unary!(trunc_s_toi64);
-unary!(trunc_u_toi64);
-unary!(extend_s);
-unary!(extend_u);
-unary!(convert_s_tof32);
-unary!(convert_u_tof32);
-unary!(convert_s_tof64);
-unary!(convert_u_tof64);
-unary!(demote);
-unary!(promote);
+convert!(i32_wrap_i64, i64, |v| v as i32);
+convert!(i32_trunc_s_f32, f32, |v| Trunc::<i32>::trunc_s(v)?);
+convert!(i32_trunc_u_f32, f32, |v| Trunc::<i32>::trunc_u(v)?);
+convert!(i32_trunc_s_f64, f64, |v| Trunc::<i32>::trunc_s(v)?);
+convert!(i32_trunc_u_f64, f64, |v| Trunc::<i32>::trunc_u(v)?);
+convert!(i64_extend_s_i32, i32, |v| i64::from(v));
+convert!(i64_extend_u_i32, i32, |v| i64::from(v as u32));
+convert!(i64_trunc_s_f32, f32, |v| Trunc::<i64>::trunc_s(v)?);
+convert!(i64_trunc_u_f32, f32, |v| Trunc::<i64>::trunc_u(v)?);
+convert!(i64_trunc_s_f64, f64, |v| Trunc::<i64>::trunc_s(v)?);
+convert!(i64_trunc_u_f64, f64, |v| Trunc::<i64>::trunc_u(v)?);
+convert!(i32_trunc_sat_s_f32, f32 => i32, |v| v.trunc_sat_s());
+convert!(i32_trunc_sat_u_f32, f32 => i32, |v| v.trunc_sat_u());
+convert!(i32_trunc_sat_s_f64, f64 => i32, |v| v.trunc_sat_s());
+convert!(i32_trunc_sat_u_f64, f64 => i32, |v| v.trunc_sat_u());
+convert!(i64_trunc_sat_s_f32, f32 => i64, |v| v.trunc_sat_s());
+convert!(i64_trunc_sat_u_f32, f32 => i64, |v| v.trunc_sat_u());
+convert!(i64_trunc_sat_s_f64, f64 => i64, |v| v.trunc_sat_s());
+convert!(i64_trunc_sat_u_f64, f64 => i64, |v| v.trunc_sat_u());
+convert!(f32_convert_s_i32, i32, |v| Convert::<f32>::convert_s(v)?);
+convert!(f32_convert_u_i32, i32, |v| Convert::<f32>::convert_u(v)?);
+convert!(f32_convert_s_i64, i64, |v| Convert::<f32>::convert_s(v)?);
+convert!(f32_convert_u_i64, i64, |v| Convert::<f32>::convert_u(v)?);
+convert!(f32_demote_f64, f64, |v| v as f32);
+convert!(f64_convert_s_i32, i32, |v| Convert::<f64>::convert_s(v)?);
+convert!(f64_convert_u_i32, i32, |v| Convert::<f64>::convert_u(v)?);
+convert!(f64_convert_s_i64, i64, |v| Convert::<f64>::convert_s(v)?);
+convert!(f64_convert_u_i64, i64, |v| Convert::<f64>::convert_u(v)?);
+convert!(f64_promote_f32, f32, |v| f64::from(v));
+convert!(i32_extend8_s, i32, |v| i32::from(v as i8));
+convert!(i32_extend16_s, i32, |v| i32::from(v as i16));
+convert!(i64_extend8_s, i64, |v| i64::from(v as i8));
+convert!(i64_extend16_s, i64, |v| i64::from(v as i16));
+convert!(i64_extend32_s, i64, |v| i64::from(v as i32));
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::types::RuntimeValue;
 
     #[test]
     fn test_reinterpret() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I64(-6_510_698_342_184_737_371)); // 0xA5A5_5A5A_5A5A_A5A5
-        wrap(&mut stack)?;
+        i32_wrap_i64(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(0x5A5A_A5A5)]);
         Ok(())
     }
@@ -45,7 +74,7 @@ mod tests {
     fn test_f32_trunc_s_toi32() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(-1.123));
-        trunc_s_toi32(&mut stack)?;
+        i32_trunc_s_f32(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-1)]);
         Ok(())
     }
@@ -54,7 +83,7 @@ mod tests {
     fn test_f64_trunc_s_toi32() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(-2.423));
-        trunc_s_toi32(&mut stack)?;
+        i32_trunc_s_f64(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-2)]);
         Ok(())
     }
@@ -63,7 +92,7 @@ mod tests {
     fn test_f32_trunc_u_toi32() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(1.123));
-        trunc_s_toi32(&mut stack)?;
+        i32_trunc_s_f32(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1)]);
         Ok(())
     }
@@ -72,7 +101,7 @@ mod tests {
     fn test_f64_trunc_s_toi64() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(-2.423));
-        trunc_s_toi64(&mut stack)?;
+        i64_trunc_s_f64(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I64(-2)]);
         Ok(())
     }
@@ -81,8 +110,57 @@ mod tests {
     fn test_f32_trunc_u_toi64() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(1.123));
-        trunc_s_toi64(&mut stack)?;
+        i64_trunc_s_f32(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I64(1)]);
         Ok(())
     }
+
+    #[test]
+    fn test_sign_extension() -> Result<(), RuntimeError> {
+        let mut stack = ValueStack::new();
+        stack.push(RuntimeValue::I32(0x0000_8080));
+        i32_extend8_s(&mut stack)?;
+        stack.push(RuntimeValue::I32(0x0001_8000));
+        i32_extend16_s(&mut stack)?;
+        stack.push(RuntimeValue::I64(0x0123_4567_8000_007f));
+        i64_extend8_s(&mut stack)?;
+        stack.push(RuntimeValue::I64(0x0123_4567_8000_7fff));
+        i64_extend16_s(&mut stack)?;
+        stack.push(RuntimeValue::I64(0x0123_4567_8000_7fff));
+        i64_extend32_s(&mut stack)?;
+        assert_eq!(
+            stack.take_buf(),
+            vec![
+                RuntimeValue::I32(-128),
+                RuntimeValue::I32(-0x8000),
+                RuntimeValue::I64(0x7f),
+                RuntimeValue::I64(0x7fff),
+                RuntimeValue::I64(-0x7fff_8001),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_trunc_sat() -> Result<(), RuntimeError> {
+        let mut stack = ValueStack::new();
+        stack.push(RuntimeValue::F32(f32::NAN));
+        i32_trunc_sat_s_f32(&mut stack)?;
+        stack.push(RuntimeValue::F64(-1.5));
+        i32_trunc_sat_u_f64(&mut stack)?;
+        stack.push(RuntimeValue::F32(1e20));
+        i64_trunc_sat_s_f32(&mut stack)?;
+        stack.push(RuntimeValue::F64(f64::INFINITY));
+        i64_trunc_sat_u_f64(&mut stack)?;
+        assert_eq!(
+            stack.take_buf(),
+            vec![
+                RuntimeValue::I32(0),
+                RuntimeValue::I32(0),
+                RuntimeValue::I64(i64::max_value()),
+                RuntimeValue::I64(-1),
+            ]
+        );
+        Ok(())
+    }
 }
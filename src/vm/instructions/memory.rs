@@ -1,159 +1,73 @@
-use std::mem;
-
 use crate::types::*;
 use crate::vm::error::RuntimeError;
-use crate::vm::memory::{MemoryRef, PAGE_SIZE};
+use crate::vm::memory::MemoryRef;
+use crate::vm::trap::TrapCode;
 use crate::vm::value_stack::ValueStack;
 
 use super::pop::*;
 
-fn validate_bounds(addr: u64, type_size: usize, current_size: usize) -> Result<(), RuntimeError> {
-    if addr + type_size as u64 > current_size as u64 * PAGE_SIZE as u64 {
-        return Err(RuntimeError::OutOfBoundsMemoryAccessError);
-    }
-    Ok(())
+fn effective_address(offset: u32, stack: &mut ValueStack) -> Result<u64, RuntimeError> {
+    let addr: i32 = pop_as(stack)?;
+    Ok(u64::from(addr as u32) + u64::from(offset))
 }
 
 macro_rules! load {
-    ($name: ident, $loader: ident, $convert_type: ty, $ret_type: ty, $ret_runtime_value: expr) => {
+    ($name: ident, $memory_type: ty, $ret_type: ty) => {
         pub fn $name(
-            operands: &[Operand],
+            offset: u32,
             stack: &mut ValueStack,
             memory_ref: &MemoryRef,
         ) -> Result<(), RuntimeError> {
-            // index 0 is unused flags
-            let offset: u32 = operands[1].into();
-            let addr: u32 = pop(stack)?.into();
-            validate_bounds(
-                addr as u64 + offset as u64,
-                mem::size_of::<$convert_type>(),
-                memory_ref.current(),
-            )?;
-            let v = memory_ref.$loader(addr + offset)? as $convert_type;
-            stack.push($ret_runtime_value(v as $ret_type));
+            let addr = effective_address(offset, stack)?;
+            let v: $memory_type = memory_ref.guest_load(addr)?;
+            stack.push(RuntimeValue::from(v as $ret_type));
             Ok(())
         }
     };
 }
 
-load!(i32_load, i32_load, i32, i32, RuntimeValue::I32);
-load!(i64_load, i64_load, i64, i64, RuntimeValue::I64);
-load!(f32_load, f32_load, f32, f32, RuntimeValue::F32);
-load!(f64_load, f64_load, f64, f64, RuntimeValue::F64);
-
-load!(i32_load8_s, i8_load, i8, i32, RuntimeValue::I32);
-load!(i32_load8_u, i8_load, u8, i32, RuntimeValue::I32);
-load!(i32_load16_s, i16_load, i16, i32, RuntimeValue::I32);
-load!(i32_load16_u, i16_load, u16, i32, RuntimeValue::I32);
-
-load!(i64_load8_s, i8_load, i8, i64, RuntimeValue::I64);
-load!(i64_load8_u, i8_load, u8, i64, RuntimeValue::I64);
-load!(i64_load16_s, i16_load, i16, i64, RuntimeValue::I64);
-load!(i64_load16_u, i16_load, u16, i64, RuntimeValue::I64);
-load!(i64_load32_s, i32_load, i32, i64, RuntimeValue::I64);
-load!(i64_load32_u, i32_load, u32, i64, RuntimeValue::I64);
+load!(i32_load, i32, i32);
+load!(i64_load, i64, i64);
+load!(f32_load, f32, f32);
+load!(f64_load, f64, f64);
 
-macro_rules! i32_store {
-    ($name: ident, $writer: ident, $convert_type: ty) => {
-        pub fn $name(
-            operands: &[Operand],
-            stack: &mut ValueStack,
-            memory_ref: &MemoryRef,
-        ) -> Result<(), RuntimeError> {
-            // index 0 is unused flags
-            let offset: u32 = operands[1].into();
-            let data = pop(stack)?;
-            let addr: u32 = pop(stack)?.into();
-            validate_bounds(
-                addr as u64 + offset as u64,
-                mem::size_of::<$convert_type>(),
-                memory_ref.current(),
-            )?;
-            if let RuntimeValue::I32(v) = data {
-                memory_ref.$writer(addr + offset, v as $convert_type)?;
-                Ok(())
-            } else {
-                Err(RuntimeError::TypeMismatchOperationError)
-            }
-        }
-    };
-}
+load!(i32_load8_s, i8, i32);
+load!(i32_load8_u, u8, i32);
+load!(i32_load16_s, i16, i32);
+load!(i32_load16_u, u16, i32);
 
-i32_store!(i32_store, i32_store, i32);
-i32_store!(i32_store8, i8_store, i8);
-i32_store!(i32_store16, i16_store, i16);
+load!(i64_load8_s, i8, i64);
+load!(i64_load8_u, u8, i64);
+load!(i64_load16_s, i16, i64);
+load!(i64_load16_u, u16, i64);
+load!(i64_load32_s, i32, i64);
+load!(i64_load32_u, u32, i64);
 
-macro_rules! i64_store {
-    ($name: ident, $writer: ident, $convert_type: ty) => {
+macro_rules! store {
+    ($name: ident, $value_type: ty, $memory_type: ty) => {
         pub fn $name(
-            operands: &[Operand],
+            offset: u32,
             stack: &mut ValueStack,
             memory_ref: &MemoryRef,
         ) -> Result<(), RuntimeError> {
-            // index 0 is unused flags
-            let offset: u32 = operands[1].into();
-            let data = pop(stack)?;
-            let addr: u32 = pop(stack)?.into();
-            validate_bounds(
-                addr as u64 + offset as u64,
-                mem::size_of::<$convert_type>(),
-                memory_ref.current(),
-            )?;
-            if let RuntimeValue::I64(v) = data {
-                memory_ref.$writer(addr + offset, v as $convert_type)?;
-                return Ok(());
-            }
-            Err(RuntimeError::TypeMismatchOperationError)
+            let v: $value_type = pop_as(stack)?;
+            let addr = effective_address(offset, stack)?;
+            memory_ref.guest_store(addr, v as $memory_type)
         }
     };
 }
 
-i64_store!(i64_store, i64_store, i64);
-i64_store!(i64_store8, i8_store, i8);
-i64_store!(i64_store16, i16_store, i16);
-i64_store!(i64_store32, i32_store, i32);
+store!(i32_store, i32, i32);
+store!(i64_store, i64, i64);
+store!(f32_store, f32, f32);
+store!(f64_store, f64, f64);
 
-pub fn f32_store(
-    operands: &[Operand],
-    stack: &mut ValueStack,
-    memory_ref: &MemoryRef,
-) -> Result<(), RuntimeError> {
-    // index 0 is unused flags
-    let offset: u32 = operands[1].into();
-    let data = pop(stack)?;
-    let addr: u32 = pop(stack)?.into();
-    validate_bounds(
-        u64::from(addr) + u64::from(offset),
-        mem::size_of::<f32>(),
-        memory_ref.current(),
-    )?;
-    if let RuntimeValue::F32(v) = data {
-        memory_ref.f32_store(addr + offset, v)?;
-        return Ok(());
-    }
-    Err(RuntimeError::TypeMismatchOperationError)
-}
+store!(i32_store8, i32, i8);
+store!(i32_store16, i32, i16);
 
-pub fn f64_store(
-    operands: &[Operand],
-    stack: &mut ValueStack,
-    memory_ref: &MemoryRef,
-) -> Result<(), RuntimeError> {
-    // index 0 is unused flags
-    let offset: u32 = operands[1].into();
-    let data = pop(stack)?;
-    let addr: u32 = pop(stack)?.into();
-    validate_bounds(
-        u64::from(addr) + u64::from(offset),
-        mem::size_of::<f64>(),
-        memory_ref.current(),
-    )?;
-    if let RuntimeValue::F64(v) = data {
-        memory_ref.f64_store(addr + offset, v)?;
-        return Ok(());
-    }
-    Err(RuntimeError::TypeMismatchOperationError)
-}
+store!(i64_store8, i64, i8);
+store!(i64_store16, i64, i16);
+store!(i64_store32, i64, i32);
 
 pub fn current(stack: &mut ValueStack, memory_ref: &MemoryRef) -> Result<(), RuntimeError> {
     let current = memory_ref.current();
@@ -162,8 +76,38 @@ pub fn current(stack: &mut ValueStack, memory_ref: &MemoryRef) -> Result<(), Run
 }
 
 pub fn grow(stack: &mut ValueStack, memory_ref: &MemoryRef) -> Result<(), RuntimeError> {
-    let size: u32 = pop(stack)?.into();
+    let size = pop_u32(stack)?;
     let res = memory_ref.grow(size);
     stack.push(RuntimeValue::I32(res));
     Ok(())
 }
+
+/// `memory.init`, where `data` is empty once the segment is dropped.
+pub fn memory_init(
+    stack: &mut ValueStack,
+    memory_ref: &MemoryRef,
+    data: &[u8],
+) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let s = pop_u32(stack)?;
+    let d = pop_u32(stack)?;
+    let (n, s) = (n as usize, s as usize);
+    let data = data
+        .get(s..s + n)
+        .ok_or(TrapCode::OutOfBoundsMemoryAccess)?;
+    memory_ref.set(data, d as usize)
+}
+
+pub fn memory_copy(stack: &mut ValueStack, memory_ref: &MemoryRef) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let s = pop_u32(stack)?;
+    let d = pop_u32(stack)?;
+    memory_ref.copy_within(s as usize, d as usize, n as usize)
+}
+
+pub fn memory_fill(stack: &mut ValueStack, memory_ref: &MemoryRef) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let value = pop_u32(stack)?;
+    let d = pop_u32(stack)?;
+    memory_ref.fill(d as usize, value as u8, n as usize)
+}
@@ -7,7 +7,9 @@ pub(crate) mod numeric;
 pub(crate) mod opecode;
 pub(crate) mod parametric;
 pub(crate) mod pop;
+pub(crate) mod reference;
 pub(crate) mod reinterpretation;
+pub(crate) mod table;
 pub(crate) mod variables;
 
 pub(crate) use comparison::*;
@@ -19,5 +21,7 @@ pub(crate) use numeric::*;
 pub(crate) use opecode::*;
 pub(crate) use parametric::*;
 pub(crate) use pop::*;
+pub(crate) use reference::*;
 pub(crate) use reinterpretation::*;
+pub(crate) use table::*;
 pub(crate) use variables::*;
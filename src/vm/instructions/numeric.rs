@@ -1,56 +1,98 @@
+use crate::types::{FloatingNumeric, IntegerNumeric, RuntimeValue};
 use crate::vm::pop::*;
 use crate::vm::value_stack::ValueStack;
 use crate::vm::RuntimeError;
 
 macro_rules! unary {
-    ($name: ident) => {
+    ($name: ident, $t: ty, |$v: ident| $body: expr) => {
         pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
-            let v = pop(stack)?;
-            stack.push(v.$name()?);
+            let $v: $t = pop_as(stack)?;
+            stack.push(RuntimeValue::from($body));
             Ok(())
         }
     };
 }
 
 macro_rules! binary {
-    ($name: ident) => {
+    ($name: ident, $t: ty, |$l: ident, $r: ident| $body: expr) => {
         pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
-            let (left, right) = pop_lr(stack)?;
-            stack.push(left.$name(&right)?);
+            let $r: $t = pop_as(stack)?;
+            let $l: $t = pop_as(stack)?;
+            stack.push(RuntimeValue::from($body));
             Ok(())
         }
     };
 }
 
-unary!(clz);
-unary!(ctz);
-unary!(popcnt);
-binary!(add);
-binary!(sub);
-binary!(mul);
-binary!(div_s);
-binary!(div_u);
-binary!(rem_s);
-binary!(rem_u);
-binary!(and);
-binary!(or);
-binary!(xor);
-binary!(shl);
-binary!(shr_s);
-binary!(shr_u);
-binary!(rotl);
-binary!(rotr);
-unary!(abs);
-unary!(neg);
-unary!(ceil);
-unary!(floor);
-unary!(trunc);
-unary!(nearest);
-unary!(sqrt);
-binary!(div);
-binary!(min);
-binary!(max);
-binary!(copysign);
+unary!(i32_clz, i32, |v| v.leading_zeros() as i32);
+unary!(i32_ctz, i32, |v| v.trailing_zeros() as i32);
+unary!(i32_popcnt, i32, |v| v.count_ones() as i32);
+binary!(i32_add, i32, |l, r| l.wrapping_add(r));
+binary!(i32_sub, i32, |l, r| l.wrapping_sub(r));
+binary!(i32_mul, i32, |l, r| l.wrapping_mul(r));
+binary!(i32_div_s, i32, |l, r| l.div_s(r)?);
+binary!(i32_div_u, i32, |l, r| l.div_u(r)?);
+binary!(i32_rem_s, i32, |l, r| l.rem_s(r)?);
+binary!(i32_rem_u, i32, |l, r| l.rem_u(r)?);
+binary!(i32_and, i32, |l, r| l & r);
+binary!(i32_or, i32, |l, r| l | r);
+binary!(i32_xor, i32, |l, r| l ^ r);
+binary!(i32_shl, i32, |l, r| l.wrapping_shl(r as u32));
+binary!(i32_shr_s, i32, |l, r| l.wrapping_shr(r as u32));
+binary!(i32_shr_u, i32, |l, r| (l as u32).wrapping_shr(r as u32)
+    as i32);
+binary!(i32_rotl, i32, |l, r| l.rotate_left(r as u32));
+binary!(i32_rotr, i32, |l, r| l.rotate_right(r as u32));
+
+unary!(i64_clz, i64, |v| v.leading_zeros() as i64);
+unary!(i64_ctz, i64, |v| v.trailing_zeros() as i64);
+unary!(i64_popcnt, i64, |v| v.count_ones() as i64);
+binary!(i64_add, i64, |l, r| l.wrapping_add(r));
+binary!(i64_sub, i64, |l, r| l.wrapping_sub(r));
+binary!(i64_mul, i64, |l, r| l.wrapping_mul(r));
+binary!(i64_div_s, i64, |l, r| l.div_s(r)?);
+binary!(i64_div_u, i64, |l, r| l.div_u(r)?);
+binary!(i64_rem_s, i64, |l, r| l.rem_s(r)?);
+binary!(i64_rem_u, i64, |l, r| l.rem_u(r)?);
+binary!(i64_and, i64, |l, r| l & r);
+binary!(i64_or, i64, |l, r| l | r);
+binary!(i64_xor, i64, |l, r| l ^ r);
+binary!(i64_shl, i64, |l, r| l.wrapping_shl(r as u32));
+binary!(i64_shr_s, i64, |l, r| l.wrapping_shr(r as u32));
+binary!(i64_shr_u, i64, |l, r| (l as u64).wrapping_shr(r as u32)
+    as i64);
+binary!(i64_rotl, i64, |l, r| l.rotate_left(r as u32));
+binary!(i64_rotr, i64, |l, r| l.rotate_right(r as u32));
+
+unary!(f32_abs, f32, |v| v.abs());
+unary!(f32_neg, f32, |v| -v);
+unary!(f32_ceil, f32, |v| v.ceil());
+unary!(f32_floor, f32, |v| v.floor());
+unary!(f32_trunc, f32, |v| v.trunc());
+unary!(f32_nearest, f32, |v| FloatingNumeric::nearest(v));
+unary!(f32_sqrt, f32, |v| v.sqrt());
+binary!(f32_add, f32, |l, r| l + r);
+binary!(f32_sub, f32, |l, r| l - r);
+binary!(f32_mul, f32, |l, r| l * r);
+binary!(f32_div, f32, |l, r| l / r);
+binary!(f32_min, f32, |l, r| l.min(r));
+binary!(f32_max, f32, |l, r| l.max(r));
+binary!(f32_copysign, f32, |l, r| l.copysign(r));
+
+unary!(f64_abs, f64, |v| v.abs());
+unary!(f64_neg, f64, |v| -v);
+unary!(f64_ceil, f64, |v| v.ceil());
+unary!(f64_floor, f64, |v| v.floor());
+unary!(f64_trunc, f64, |v| v.trunc());
+unary!(f64_nearest, f64, |v| FloatingNumeric::nearest(v));
+unary!(f64_sqrt, f64, |v| v.sqrt());
+binary!(f64_add, f64, |l, r| l + r);
+binary!(f64_sub, f64, |l, r| l - r);
+binary!(f64_mul, f64, |l, r| l * r);
+binary!(f64_div, f64, |l, r| l / r);
+binary!(f64_min, f64, |l, r| l.min(r));
+binary!(f64_max, f64, |l, r| l.max(r));
+binary!(f64_copysign, f64, |l, r| l.copysign(r));
 
 #[cfg(test)]
 mod tests {
@@ -62,7 +104,7 @@ mod tests {
     fn test_i32clz() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x0000_FFFF));
-        clz(&mut stack)?;
+        i32_clz(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(16)]);
         Ok(())
     }
@@ -71,7 +113,7 @@ mod tests {
     fn test_i32ctz() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x0000_FF00));
-        ctz(&mut stack)?;
+        i32_ctz(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(8)]);
         Ok(())
     }
@@ -80,7 +122,7 @@ mod tests {
     fn test_i32popcnt() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(0x5A5A_A5A5));
-        popcnt(&mut stack)?;
+        i32_popcnt(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(16)]);
         Ok(())
     }
@@ -90,7 +132,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(10.0));
         stack.push(RuntimeValue::F64(20.1));
-        add(&mut stack)?;
+        f64_add(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(30.1)]);
         Ok(())
     }
@@ -100,7 +142,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(4));
         stack.push(RuntimeValue::I32(-2));
-        add(&mut stack)?;
+        i32_add(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(2)],);
         Ok(())
     }
@@ -110,7 +152,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(100));
         stack.push(RuntimeValue::I32(200));
-        mul(&mut stack)?;
+        i32_mul(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(20_000)]);
         Ok(())
     }
@@ -120,7 +162,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(0.1));
         stack.push(RuntimeValue::F32(0.2));
-        mul(&mut stack)?;
+        f32_mul(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F32(0.020_000_001)]);
         Ok(())
     }
@@ -130,7 +172,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        div_s(&mut stack)?;
+        i32_div_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-2)]);
         Ok(())
     }
@@ -140,7 +182,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        div_u(&mut stack)?;
+        i32_div_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(2_147_483_646)]);
         Ok(())
     }
@@ -150,7 +192,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-37));
         stack.push(RuntimeValue::I32(10));
-        rem_s(&mut stack)?;
+        i32_rem_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-7)]);
         Ok(())
     }
@@ -160,7 +202,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-37));
         stack.push(RuntimeValue::I32(10));
-        rem_u(&mut stack)?;
+        i32_rem_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(9)]);
         Ok(())
     }
@@ -170,7 +212,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        shr_s(&mut stack)?;
+        i32_shr_s(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-1)]);
         Ok(())
     }
@@ -180,7 +222,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        shr_u(&mut stack)?;
+        i32_shr_u(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1_073_741_823)]);
         Ok(())
     }
@@ -190,7 +232,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        rotl(&mut stack)?;
+        i32_rotl(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(-13)]);
         Ok(())
     }
@@ -200,7 +242,7 @@ mod tests {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::I32(-4));
         stack.push(RuntimeValue::I32(2));
-        rotr(&mut stack)?;
+        i32_rotr(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1_073_741_823)]);
         Ok(())
     }
@@ -209,7 +251,7 @@ mod tests {
     fn test_f64abs() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(-4.5));
-        abs(&mut stack)?;
+        f64_abs(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(4.5)]);
         Ok(())
     }
@@ -218,7 +260,7 @@ mod tests {
     fn test_f64neg() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(4.5));
-        neg(&mut stack)?;
+        f64_neg(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(-4.5)]);
         Ok(())
     }
@@ -227,7 +269,7 @@ mod tests {
     fn test_f64ceil() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(4.5));
-        ceil(&mut stack)?;
+        f64_ceil(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(5.0)]);
         Ok(())
     }
@@ -236,7 +278,7 @@ mod tests {
     fn test_f64floor() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(4.79));
-        floor(&mut stack)?;
+        f64_floor(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(4.0)]);
         Ok(())
     }
@@ -245,7 +287,7 @@ mod tests {
     fn test_f64nearest() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(4.49));
-        nearest(&mut stack)?;
+        f64_nearest(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(4.0)]);
         Ok(())
     }
@@ -254,7 +296,7 @@ mod tests {
     fn test_f64trunc() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(-4.49));
-        trunc(&mut stack)?;
+        f64_trunc(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(-4.0)]);
         Ok(())
     }
@@ -263,7 +305,7 @@ mod tests {
     fn test_f64sqrt() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F64(1.44));
-        sqrt(&mut stack)?;
+        f64_sqrt(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::F64(1.2)]);
         Ok(())
     }
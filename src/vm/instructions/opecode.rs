@@ -1,6 +1,7 @@
 use num_derive::*;
 
-#[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
+/// Opcodes behind a prefix byte are numbered `prefix << 8 | sub-opcode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum Opcode {
     Unreachable = 0x00,
     Nop = 0x01,
@@ -17,11 +18,14 @@ pub enum Opcode {
     CallIndirect = 0x11,
     Drop = 0x1A,
     Select = 0x1B,
+    TypedSelect = 0x1C,
     GetLocal = 0x20,
     SetLocal = 0x21,
     TeeLocal = 0x22,
     GetGlobal = 0x23,
     SetGlobal = 0x24,
+    TableGet = 0x25,
+    TableSet = 0x26,
     I32Load = 0x28,
     I64Load = 0x29,
     F32Load = 0x2A,
@@ -174,4 +178,36 @@ pub enum Opcode {
     I64ReinterpretF64 = 0xBD,
     F32ReinterpretI32 = 0xBE,
     F64ReinterpretI64 = 0xBF,
+    I32Extend8S = 0xC0,
+    I32Extend16S = 0xC1,
+    I64Extend8S = 0xC2,
+    I64Extend16S = 0xC3,
+    I64Extend32S = 0xC4,
+    RefNull = 0xD0,
+    RefIsNull = 0xD1,
+    RefFunc = 0xD2,
+    I32TruncSatSF32 = 0xFC00,
+    I32TruncSatUF32 = 0xFC01,
+    I32TruncSatSF64 = 0xFC02,
+    I32TruncSatUF64 = 0xFC03,
+    I64TruncSatSF32 = 0xFC04,
+    I64TruncSatUF32 = 0xFC05,
+    I64TruncSatSF64 = 0xFC06,
+    I64TruncSatUF64 = 0xFC07,
+    MemoryInit = 0xFC08,
+    DataDrop = 0xFC09,
+    MemoryCopy = 0xFC0A,
+    MemoryFill = 0xFC0B,
+    TableInit = 0xFC0C,
+    ElemDrop = 0xFC0D,
+    TableCopy = 0xFC0E,
+    TableGrow = 0xFC0F,
+    TableSize = 0xFC10,
+    TableFill = 0xFC11,
+}
+
+impl Opcode {
+    /// Prefix of the saturating truncations, bulk memory and table operations,
+    /// followed by a `u32` sub-opcode.
+    pub const MISC_PREFIX: u8 = 0xFC;
 }
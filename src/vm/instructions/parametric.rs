@@ -1,14 +1,13 @@
-use crate::error::YawError;
 use crate::vm::value_stack::ValueStack;
 use crate::vm::RuntimeError;
 
-use super::pop;
+use super::{pop, pop_as};
 
-pub fn select(stack: &mut ValueStack) -> Result<(), YawError> {
-    let op3 = stack.pop().unwrap();
-    let op2 = stack.pop().unwrap();
-    let op1 = stack.pop().unwrap();
-    if op3.is_zero() {
+pub fn select(stack: &mut ValueStack) -> Result<(), RuntimeError> {
+    let condition: i32 = pop_as(stack)?;
+    let op2 = pop(stack)?;
+    let op1 = pop(stack)?;
+    if condition == 0 {
         stack.push(op2);
     } else {
         stack.push(op1);
@@ -25,6 +24,7 @@ pub fn drop(stack: &mut ValueStack) -> Result<(), RuntimeError> {
 mod tests {
 
     use super::*;
+    use crate::error::YawError;
     use crate::types::RuntimeValue;
 
     #[test]
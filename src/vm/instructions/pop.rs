@@ -1,14 +1,20 @@
-use crate::types::RuntimeValue;
-use crate::vm::value_stack::ValueStack;
+use crate::types::{FromRuntimeValue, RuntimeValue};
 use crate::vm::error::RuntimeError;
-
-pub fn pop_lr(stack: &mut ValueStack) -> Result<(RuntimeValue, RuntimeValue), RuntimeError> {
-    let r = stack.pop().ok_or(RuntimeError::StackPopError)?;
-    let l = stack.pop().ok_or(RuntimeError::StackPopError)?;
-    Ok((l, r))
-}
+use crate::vm::value_stack::ValueStack;
 
 pub fn pop(stack: &mut ValueStack) -> Result<RuntimeValue, RuntimeError> {
     let v = stack.pop().ok_or(RuntimeError::StackPopError)?;
     Ok(v)
 }
+
+/// Pops an operand of a statically typed instruction.
+pub fn pop_as<T: FromRuntimeValue>(stack: &mut ValueStack) -> Result<T, RuntimeError> {
+    let v = stack.pop().ok_or(RuntimeError::StackPopError)?;
+    T::from_runtime_value(v).ok_or(RuntimeError::TypeError)
+}
+
+/// Pops an `i32` operand that is used as an unsigned index, address or length.
+pub fn pop_u32(stack: &mut ValueStack) -> Result<u32, RuntimeError> {
+    let v: i32 = pop_as(stack)?;
+    Ok(v as u32)
+}
@@ -0,0 +1,43 @@
+use crate::types::*;
+use crate::vm::error::RuntimeError;
+use crate::vm::value_stack::ValueStack;
+use crate::vm::FunctionInstanceRef;
+
+use super::pop::*;
+
+pub fn ref_null(value_type: ValueType, stack: &mut ValueStack) -> Result<(), RuntimeError> {
+    stack.push(value_type.into());
+    Ok(())
+}
+
+pub fn ref_is_null(stack: &mut ValueStack) -> Result<(), RuntimeError> {
+    let value = pop(stack)?;
+    stack.push(RuntimeValue::I32(value.is_null() as i32));
+    Ok(())
+}
+
+pub fn ref_func(func: FunctionInstanceRef, stack: &mut ValueStack) -> Result<(), RuntimeError> {
+    stack.push(RuntimeValue::FuncRef(Some(FuncRef(func))));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::error::YawError;
+
+    #[test]
+    fn test_ref_is_null() -> Result<(), YawError> {
+        let mut stack = ValueStack::new();
+        ref_null(ValueType::ExternRef, &mut stack)?;
+        ref_is_null(&mut stack)?;
+        stack.push(RuntimeValue::ExternRef(Some(ExternRef::new(1))));
+        ref_is_null(&mut stack)?;
+        assert_eq!(
+            stack.take_buf(),
+            vec![RuntimeValue::I32(1), RuntimeValue::I32(0)]
+        );
+        Ok(())
+    }
+}
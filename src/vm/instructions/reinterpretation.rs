@@ -1,25 +1,33 @@
-use crate::error::YawError;
-
+use crate::types::RuntimeValue;
+use crate::vm::error::RuntimeError;
 use crate::vm::pop::*;
 use crate::vm::value_stack::ValueStack;
 
-pub fn reinterpret(stack: &mut ValueStack) -> Result<(), YawError> {
-    let v = pop(stack)?;
-    stack.push(v.reinterpret()?);
-    Ok(())
+macro_rules! reinterpret {
+    ($name: ident, $from: ty, |$v: ident| $body: expr) => {
+        pub fn $name(stack: &mut ValueStack) -> Result<(), RuntimeError> {
+            let $v: $from = pop_as(stack)?;
+            stack.push(RuntimeValue::from($body));
+            Ok(())
+        }
+    };
 }
 
+reinterpret!(i32_reinterpret_f32, f32, |v| v.to_bits() as i32);
+reinterpret!(i64_reinterpret_f64, f64, |v| v.to_bits() as i64);
+reinterpret!(f32_reinterpret_i32, i32, |v| f32::from_bits(v as u32));
+reinterpret!(f64_reinterpret_i64, i64, |v| f64::from_bits(v as u64));
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::types::RuntimeValue;
 
     #[test]
-    fn test_reinterpret() -> Result<(), YawError> {
+    fn test_reinterpret() -> Result<(), RuntimeError> {
         let mut stack = ValueStack::new();
         stack.push(RuntimeValue::F32(1.0));
-        reinterpret(&mut stack)?;
+        i32_reinterpret_f32(&mut stack)?;
         assert_eq!(stack.take_buf(), vec![RuntimeValue::I32(1_065_353_216)]);
         Ok(())
     }
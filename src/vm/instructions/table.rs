@@ -0,0 +1,79 @@
+use std::rc::Rc;
+
+use crate::types::*;
+use crate::vm::error::RuntimeError;
+use crate::vm::table::TableRef;
+use crate::vm::trap::TrapCode;
+use crate::vm::value_stack::ValueStack;
+
+use super::pop::*;
+
+pub fn table_get(stack: &mut ValueStack, table: &TableRef) -> Result<(), RuntimeError> {
+    let index = pop_u32(stack)?;
+    let value = table.borrow().get(index as usize)?;
+    stack.push(value);
+    Ok(())
+}
+
+pub fn table_set(stack: &mut ValueStack, table: &TableRef) -> Result<(), RuntimeError> {
+    let value = pop(stack)?;
+    let index = pop_u32(stack)?;
+    table.borrow_mut().set(index as usize, value)
+}
+
+pub fn table_size(stack: &mut ValueStack, table: &TableRef) -> Result<(), RuntimeError> {
+    let size = table.borrow().size();
+    stack.push(RuntimeValue::I32(size as i32));
+    Ok(())
+}
+
+pub fn table_grow(stack: &mut ValueStack, table: &TableRef) -> Result<(), RuntimeError> {
+    let delta = pop_u32(stack)?;
+    let init = pop(stack)?;
+    let res = table.borrow_mut().grow(delta, init);
+    stack.push(RuntimeValue::I32(res));
+    Ok(())
+}
+
+pub fn table_fill(stack: &mut ValueStack, table: &TableRef) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let value = pop(stack)?;
+    let d = pop_u32(stack)?;
+    table.borrow_mut().fill(d as usize, value, n as usize)
+}
+
+/// `table.init`, where `elems` is empty once the segment is dropped.
+pub fn table_init(
+    stack: &mut ValueStack,
+    table: &TableRef,
+    elems: &[RuntimeValue],
+) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let s = pop_u32(stack)?;
+    let d = pop_u32(stack)?;
+    let (n, s) = (n as usize, s as usize);
+    let elems = elems
+        .get(s..s + n)
+        .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+    table.borrow_mut().init(d as usize, elems)
+}
+
+pub fn table_copy(
+    stack: &mut ValueStack,
+    dst: &TableRef,
+    src: &TableRef,
+) -> Result<(), RuntimeError> {
+    let n = pop_u32(stack)?;
+    let s = pop_u32(stack)?;
+    let d = pop_u32(stack)?;
+    let (n, s, d) = (n as usize, s as usize, d as usize);
+    if Rc::ptr_eq(dst, src) {
+        return dst.borrow_mut().copy_within(s, d, n);
+    }
+    let src = src.borrow();
+    let elems = src
+        .entries
+        .get(s..s + n)
+        .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+    dst.borrow_mut().init(d, elems)
+}
@@ -1,66 +1,52 @@
-use crate::types::*;
 use crate::vm::{Globals, RuntimeError};
 
 use super::super::value_stack::ValueStack;
 use super::pop;
 
-pub fn get_local(
-    operands: &[Operand],
-    stack: &mut ValueStack,
-    locals: &[RuntimeValue],
-) -> Result<(), RuntimeError> {
-    let index: usize = operands[0].into();
-    let value = locals[index];
+pub fn get_local(index: u32, stack: &mut ValueStack, base: usize) -> Result<(), RuntimeError> {
+    let value = stack
+        .get(base + index as usize)
+        .ok_or(RuntimeError::StackPopError)?;
     stack.push(value);
     Ok(())
 }
 
-pub fn set_local(
-    operands: &[Operand],
-    stack: &mut ValueStack,
-    locals: &mut [RuntimeValue],
-) -> Result<(), RuntimeError> {
-    let index: usize = operands[0].into();
+pub fn set_local(index: u32, stack: &mut ValueStack, base: usize) -> Result<(), RuntimeError> {
     let value = pop(stack)?;
-    locals[index] = value;
-    Ok(())
+    stack
+        .set(base + index as usize, value)
+        .ok_or(RuntimeError::StackPopError)
 }
 
-pub fn tee_local(
-    operands: &[Operand],
-    stack: &mut ValueStack,
-    locals: &mut [RuntimeValue],
-) -> Result<(), RuntimeError> {
-    let index: usize = operands[0].into();
+pub fn tee_local(index: u32, stack: &mut ValueStack, base: usize) -> Result<(), RuntimeError> {
     let value = pop(stack)?;
-    locals[index] = value;
-    stack.push(value);
-    Ok(())
+    stack.push(value.clone());
+    stack
+        .set(base + index as usize, value)
+        .ok_or(RuntimeError::StackPopError)
 }
 
 pub fn get_global(
-    operands: &[Operand],
+    index: u32,
     stack: &mut ValueStack,
     globals: &Globals,
 ) -> Result<(), RuntimeError> {
-    let index: usize = operands[0].into();
     let value = globals
-        .get(index)
+        .get(index as usize)
         .ok_or(RuntimeError::UndefinedGlobalError)?;
     let value = value.borrow();
-    stack.push(value.value);
+    stack.push(value.value.clone());
     Ok(())
 }
 
 pub fn set_global(
-    operands: &[Operand],
+    index: u32,
     stack: &mut ValueStack,
     globals: &Globals,
 ) -> Result<(), RuntimeError> {
-    let index: usize = operands[0].into();
     let v = pop(stack)?;
     let value = globals
-        .get(index)
+        .get(index as usize)
         .ok_or(RuntimeError::UndefinedGlobalError)?;
     let mut g = value.borrow_mut();
     g.value = v;
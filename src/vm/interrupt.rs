@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A handle to stop a running guest from another thread or a signal handler.
+/// The guest traps with `TrapCode::Interrupted` at the next loop iteration
+/// or function entry.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Requests the running invocation to stop. A request made while no guest
+    /// code runs is kept, and stops the next invocation or resumption at its
+    /// first check.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether an interruption was requested, clearing the request.
+    pub(crate) fn take(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) && self.flag.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn take_interruption_once() {
+        let handle = InterruptHandle::default();
+        assert!(!handle.take());
+        handle.clone().interrupt();
+        assert!(handle.take());
+        assert!(!handle.take());
+    }
+}
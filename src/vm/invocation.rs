@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::error::YawError;
+use crate::types::RuntimeValue;
+
+use super::call_stack::{CallStack, StackFrame};
+use super::function::{FunctionInstance, FunctionInstanceRef};
+use super::label::LabelStack;
+use super::{check_types, InternalFunction, Next, RuntimeError, ValueStack, VM};
+
+/// Why an invocation stopped before the called function returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Suspension {
+    /// The fuel budget ran out. Top it up with `VM::add_fuel` before resuming.
+    OutOfFuel,
+    /// A host function asked to yield through `FunctionResolver::yield_requested`.
+    Yield,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvocationResult {
+    Finished(Vec<RuntimeValue>),
+    Suspended(Suspension),
+}
+
+/// A call into the guest whose call stack and value stack outlive a single run,
+/// so that it can be suspended and resumed from exactly the same point.
+#[derive(Debug)]
+pub struct Invocation<'v, 'a> {
+    vm: &'v VM<'a>,
+    /// An exported host function that has not been called yet.
+    host: Option<FunctionInstanceRef>,
+    cstack: CallStack,
+    vstack: ValueStack,
+    lstack: LabelStack,
+}
+
+impl<'v, 'a> Invocation<'v, 'a> {
+    pub(crate) fn new(
+        vm: &'v VM<'a>,
+        func: &InternalFunction,
+        args: &[RuntimeValue],
+    ) -> Result<Self, RuntimeError> {
+        check_types(args, &func.args)?;
+        let mut vstack = ValueStack::new();
+        for arg in args {
+            vstack.push(arg.clone());
+        }
+        vm.push_locals(func, &mut vstack)?;
+        let mut cstack = CallStack::new();
+        let arity = func.results.len();
+        cstack.push(StackFrame::new(Rc::clone(&func.code), 0, 0, 0, arity));
+        Ok(Invocation {
+            vm,
+            host: None,
+            cstack,
+            vstack,
+            lstack: LabelStack::new(),
+        })
+    }
+
+    /// An invocation of an exported host function, which is called by the first `resume`.
+    pub(crate) fn host(vm: &'v VM<'a>, func: FunctionInstanceRef, args: &[RuntimeValue]) -> Self {
+        let mut vstack = ValueStack::new();
+        for arg in args {
+            vstack.push(arg.clone());
+        }
+        Invocation {
+            vm,
+            host: Some(func),
+            cstack: CallStack::new(),
+            vstack,
+            lstack: LabelStack::new(),
+        }
+    }
+
+    /// Runs the guest until the invoked function returns or the execution is suspended.
+    /// An invocation must not be resumed again after it returned an error.
+    pub fn resume(&mut self) -> Result<InvocationResult, YawError> {
+        if let Some(func) = self.host.take() {
+            if let FunctionInstance::ExternalFunction(func) = &*func {
+                // A yield request has nothing to suspend once the host function returned.
+                self.vm.execute_external_function(func, &mut self.vstack)?;
+            }
+        }
+        while self.cstack.len() > 0 {
+            let next =
+                self.vm
+                    .execute_function(&mut self.cstack, &mut self.vstack, &mut self.lstack)?;
+            if let Next::Suspend(reason) = next {
+                return Ok(InvocationResult::Suspended(reason));
+            }
+        }
+        Ok(InvocationResult::Finished(self.vstack.take_buf()))
+    }
+}
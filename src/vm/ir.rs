@@ -0,0 +1,319 @@
+use crate::decoder::{FuncType, Instruction};
+use crate::types::*;
+
+use super::instructions::Opcode;
+
+/// Immediate of a lowered instruction, stored inline so that executing it
+/// never touches a heap-allocated operand list.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Imm {
+    None,
+    /// Local slot, global, function, type or segment index.
+    Index(u32),
+    /// Type and table of `call_indirect`, segment and table of `table.init`,
+    /// destination and source tables of `table.copy`.
+    Indices(u32, u32),
+    /// Type of the null reference of `ref.null`.
+    RefType(ValueType),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    /// Static offset of a memory access.
+    Offset(u32),
+    /// Label depth of `br` and `br_if`.
+    Depth(u32),
+    /// Label depths of `br_table`, stored in `Code::br_tables` with the default last.
+    Table {
+        start: u32,
+        len: u32,
+    },
+    /// Numbers of params and results of a block.
+    Block {
+        params: u32,
+        results: u32,
+        end: u32,
+    },
+    Loop {
+        params: u32,
+    },
+    If {
+        params: u32,
+        results: u32,
+        els: u32,
+        end: u32,
+    },
+}
+
+impl Imm {
+    pub fn index(self) -> u32 {
+        match self {
+            Imm::Index(v) => v,
+            _ => unreachable!("instruction should have an index immediate"),
+        }
+    }
+
+    pub fn indices(self) -> (u32, u32) {
+        match self {
+            Imm::Indices(a, b) => (a, b),
+            _ => unreachable!("instruction should have two index immediates"),
+        }
+    }
+
+    pub fn depth(self) -> u32 {
+        match self {
+            Imm::Depth(v) => v,
+            _ => unreachable!("instruction should have a depth immediate"),
+        }
+    }
+
+    pub fn offset(self) -> u32 {
+        match self {
+            Imm::Offset(v) => v,
+            _ => unreachable!("instruction should have an offset immediate"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Instr {
+    pub opcode: Opcode,
+    pub imm: Imm,
+}
+
+impl Instr {
+    pub fn new(opcode: Opcode, imm: Imm) -> Instr {
+        Instr { opcode, imm }
+    }
+}
+
+/// A function body lowered from the decoded stack-machine form.
+/// Block continuations are resolved to instruction offsets at compile time,
+/// so entering a block pushes a label that already knows where to go.
+/// Operands stay on the value stack, locals are slots relative to the frame base.
+#[derive(Debug, Default)]
+pub struct Code {
+    /// Index of the function in the function index space.
+    pub func_index: u32,
+    pub instrs: Vec<Instr>,
+    pub br_tables: Vec<u32>,
+    /// Code section offset of each instruction.
+    pub offsets: Vec<u32>,
+}
+
+impl Code {
+    /// `types` are the entries of the type section, which block types may refer to.
+    pub fn compile(
+        func_index: u32,
+        body: &[Instruction],
+        offsets: Vec<u32>,
+        types: &[FuncType],
+    ) -> Code {
+        let mut instrs: Vec<Instr> = Vec::with_capacity(body.len());
+        let mut br_tables = vec![];
+        let mut opened = vec![];
+        for (pc, (opcode, operands)) in body.iter().enumerate() {
+            let imm = match opcode {
+                Opcode::Block | Opcode::Loop | Opcode::If => {
+                    opened.push(pc);
+                    let (params, results) = match operands[0] {
+                        Operand::ResultType(t) => (0, t.value_type().is_some() as u32),
+                        Operand::TypeIndex(i) => {
+                            let t = &types[i as usize];
+                            (t.args.len() as u32, t.results.len() as u32)
+                        }
+                        _ => (0, 0),
+                    };
+                    match opcode {
+                        Opcode::Block => Imm::Block {
+                            params,
+                            results,
+                            end: 0,
+                        },
+                        Opcode::Loop => Imm::Loop { params },
+                        _ => Imm::If {
+                            params,
+                            results,
+                            els: 0,
+                            end: 0,
+                        },
+                    }
+                }
+                Opcode::Else => {
+                    if let Some(&start) = opened.last() {
+                        if let Imm::If { ref mut els, .. } = instrs[start].imm {
+                            *els = pc as u32 + 1;
+                        }
+                    }
+                    Imm::None
+                }
+                Opcode::End => {
+                    if let Some(start) = opened.pop() {
+                        close(&mut instrs[start], pc + 1);
+                    }
+                    Imm::None
+                }
+                Opcode::Br | Opcode::BrIf => Imm::Depth(operands[0].into()),
+                Opcode::BrTable => {
+                    let start = br_tables.len() as u32;
+                    let count: u32 = operands[0].into();
+                    for operand in &operands[1..] {
+                        br_tables.push((*operand).into());
+                    }
+                    Imm::Table { start, len: count }
+                }
+                Opcode::Call
+                | Opcode::GetLocal
+                | Opcode::SetLocal
+                | Opcode::TeeLocal
+                | Opcode::GetGlobal
+                | Opcode::SetGlobal
+                | Opcode::MemoryInit
+                | Opcode::DataDrop
+                | Opcode::ElemDrop
+                | Opcode::TableGet
+                | Opcode::TableSet
+                | Opcode::TableGrow
+                | Opcode::TableSize
+                | Opcode::TableFill
+                | Opcode::RefFunc => Imm::Index(operands[0].into()),
+                Opcode::CallIndirect | Opcode::TableInit | Opcode::TableCopy => {
+                    Imm::Indices(operands[0].into(), operands[1].into())
+                }
+                Opcode::RefNull => match operands[0] {
+                    Operand::ValueType(t) => Imm::RefType(t),
+                    _ => Imm::None,
+                },
+                // The operand types of a typed `select` are only needed by the validator.
+                Opcode::TypedSelect => Imm::None,
+                Opcode::I32Const | Opcode::I64Const | Opcode::F32Const | Opcode::F64Const => {
+                    match operands[0] {
+                        Operand::I32(v) => Imm::I32(v),
+                        Operand::I64(v) => Imm::I64(v),
+                        Operand::F32(v) => Imm::F32(v),
+                        Operand::F64(v) => Imm::F64(v),
+                        _ => Imm::None,
+                    }
+                }
+                // Memory instructions carry [flags, offset], the alignment hint is not needed.
+                Opcode::I32Load
+                | Opcode::I64Load
+                | Opcode::F32Load
+                | Opcode::F64Load
+                | Opcode::I32Load8S
+                | Opcode::I32Load8U
+                | Opcode::I32Load16S
+                | Opcode::I32Load16U
+                | Opcode::I64Load8S
+                | Opcode::I64Load8U
+                | Opcode::I64Load16S
+                | Opcode::I64Load16U
+                | Opcode::I64Load32S
+                | Opcode::I64Load32U
+                | Opcode::I32Store
+                | Opcode::I64Store
+                | Opcode::F32Store
+                | Opcode::F64Store
+                | Opcode::I32Store8
+                | Opcode::I32Store16
+                | Opcode::I64Store8
+                | Opcode::I64Store16
+                | Opcode::I64Store32 => Imm::Offset(operands[1].into()),
+                _ => Imm::None,
+            };
+            instrs.push(Instr::new(*opcode, imm));
+        }
+        // Blocks left open are closed by the end of the function body.
+        for start in opened {
+            close(&mut instrs[start], body.len());
+        }
+        Code {
+            func_index,
+            instrs,
+            br_tables,
+            offsets,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instrs.len()
+    }
+
+    /// Code section offset of the instruction at `pc`.
+    pub fn offset(&self, pc: usize) -> Option<u32> {
+        self.offsets.get(pc).cloned()
+    }
+
+    /// Label depths of a `br_table`, followed by the default depth.
+    pub fn br_table(&self, start: u32, len: u32) -> &[u32] {
+        &self.br_tables[start as usize..=(start + len) as usize]
+    }
+}
+
+fn close(instr: &mut Instr, end_pc: usize) {
+    match instr.imm {
+        Imm::Block { ref mut end, .. } => *end = end_pc as u32,
+        Imm::If {
+            ref mut els,
+            ref mut end,
+            ..
+        } => {
+            *end = end_pc as u32;
+            if *els == 0 {
+                *els = end_pc as u32;
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn resolve_block_targets() {
+        let block = vec![Operand::ResultType(ResultType::Empty)];
+        // (param i32) (result i32 i32)
+        let types = vec![FuncType {
+            args: vec![ValueType::I32],
+            results: vec![ValueType::I32, ValueType::I32],
+        }];
+        let body = vec![
+            (Opcode::Block, block.clone()),
+            (Opcode::If, vec![Operand::TypeIndex(0)]),
+            (Opcode::Nop, vec![]),
+            (Opcode::Else, vec![]),
+            (
+                Opcode::BrTable,
+                vec![Operand::U32(1), Operand::U32(0), Operand::U32(1)],
+            ),
+            (Opcode::End, vec![]),
+            (Opcode::End, vec![]),
+            (Opcode::Loop, vec![Operand::TypeIndex(0)]),
+            (Opcode::End, vec![]),
+        ];
+        let code = Code::compile(0, &body, vec![], &types);
+        assert_eq!(
+            code.instrs[0].imm,
+            Imm::Block {
+                params: 0,
+                results: 0,
+                end: 7
+            }
+        );
+        assert_eq!(
+            code.instrs[1].imm,
+            Imm::If {
+                params: 1,
+                results: 2,
+                els: 4,
+                end: 6
+            }
+        );
+        assert_eq!(code.instrs[4].imm, Imm::Table { start: 0, len: 1 });
+        assert_eq!(code.br_table(0, 1), &[0, 1]);
+        assert_eq!(code.instrs[7].imm, Imm::Loop { params: 1 });
+    }
+}
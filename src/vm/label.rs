@@ -1,38 +1,27 @@
-use num_derive::*;
-
-use crate::types::*;
-
+/// Where execution continues when a branch targets this label.
+/// For `block` and `if` it is the instruction after the matching `end`,
+/// for `loop` it is the `loop` instruction itself.
 #[derive(Debug, Copy, Clone)]
 pub struct Label {
-    pub position: usize,
-    pub block_type: BlockType,
-    pub result_type: ResultType,
+    pub continuation: usize,
+    /// Number of values a branch carries, the params for `loop` and the results otherwise.
+    pub arity: usize,
+    /// Height of the value stack below the params of the block.
     pub sp: usize,
 }
 
 impl Label {
-    pub fn new(
-        position: usize,
-        block_type: BlockType,
-        result_type: ResultType,
-        sp: usize,
-    ) -> Label {
+    pub fn new(continuation: usize, arity: usize, sp: usize) -> Label {
         Label {
-            position,
-            block_type,
-            result_type,
+            continuation,
+            arity,
             sp,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
-pub enum BlockType {
-    Block = 0x02,
-    Loop = 0x03,
-    If = 0x04,
-}
-
+/// Labels of all active frames. A frame only sees the labels above the
+/// height the stack had when it was entered.
 #[derive(Debug)]
 pub struct LabelStack(Vec<Label>);
 
@@ -49,16 +38,20 @@ impl LabelStack {
         self.0.pop()
     }
 
-    pub fn len(&mut self) -> usize {
+    pub fn len(&self) -> usize {
         self.0.len()
     }
 
-    pub fn take_before(&mut self, depth: u32) -> Vec<Label> {
-        let depth = if depth as usize > self.0.len() {
-            self.0.len()
-        } else {
-            depth as usize
-        };
-        self.0.split_off(self.0.len() - depth as usize)
+    /// Returns the label `depth` levels out from the innermost one.
+    pub fn get(&self, depth: usize) -> Option<&Label> {
+        let len = self.0.len();
+        if depth >= len {
+            return None;
+        }
+        self.0.get(len - depth - 1)
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
     }
 }
@@ -1,20 +1,79 @@
-use std::cell::RefCell;
-use std::io::{Cursor, Read, Write};
+use std::cell::UnsafeCell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::mem;
+use std::ptr;
 use std::rc::Rc;
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-
-use super::{Globals, ImportResolver, ImportType, RuntimeError, Sections};
+use super::{Functions, Globals, ImportResolver, ImportType, RuntimeError, Sections, TrapCode};
+use crate::decoder::SegmentMode;
 
 #[derive(Debug)]
-pub struct MemoryRef(Rc<RefCell<Memory>>);
+pub struct MemoryRef(Rc<Memory>);
 
-#[derive(Debug)]
+#[cfg(not(guard_pages))]
+type Buffer = Vec<u8>;
+#[cfg(guard_pages)]
+type Buffer = super::guard::GuardedBuffer;
+
+#[cfg(not(guard_pages))]
+fn new_buffer(len: usize) -> Result<Buffer, RuntimeError> {
+    Ok(vec![0; len])
+}
+
+#[cfg(guard_pages)]
+fn new_buffer(len: usize) -> Result<Buffer, RuntimeError> {
+    Buffer::new(len)
+}
+
+/// Grows `buf` to `len` zeroed bytes, returning whether the memory could be had.
+#[cfg(not(guard_pages))]
+fn grow_buffer(buf: &mut Buffer, len: usize) -> bool {
+    if buf.try_reserve_exact(len - buf.len()).is_err() {
+        return false;
+    }
+    buf.resize(len, 0);
+    true
+}
+
+#[cfg(guard_pages)]
+fn grow_buffer(buf: &mut Buffer, len: usize) -> bool {
+    buf.resize(len).is_ok()
+}
+
+#[cfg(guard_pages)]
+use super::guard::catch_traps;
+
+/// Converts the effective address of a guarded access to an offset into the
+/// buffer, trapping if the access could reach past the address space.
+#[cfg(guard_pages)]
+fn guarded_offset<T: LittleEndianConvert>(addr: u64) -> Result<usize, RuntimeError> {
+    usize::try_from(addr)
+        .ok()
+        .filter(|offset| offset.checked_add(T::SIZE).is_some())
+        .ok_or_else(|| TrapCode::OutOfBoundsMemoryAccess.into())
+}
+
+/// The most pages a linear memory can have.
+const MAX_PAGES: usize = 0x10000;
+
+/// A linear memory.
+/// The buffer is only ever touched inside `MemoryRef::with_buf`, which never
+/// lets a reference escape or calls back into the guest or the host, so loads
+/// and stores need no borrow flag.
 pub struct Memory {
-    buf: Vec<u8>,
+    buf: UnsafeCell<Buffer>,
     maximum: Option<u32>,
 }
 
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Memory")
+            .field("maximum", &self.maximum)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct MemoryDescriptor {
     initial: u32,
@@ -35,126 +94,250 @@ impl Clone for MemoryRef {
     }
 }
 
+/// A value that is stored in linear memory in little-endian byte order.
+pub trait LittleEndianConvert: Sized {
+    const SIZE: usize;
+    fn from_le(bytes: &[u8]) -> Self;
+    fn to_le(self, bytes: &mut [u8]);
+
+    /// Reads a value at `ptr`, which need not be aligned.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads of `SIZE` bytes.
+    unsafe fn read_le(ptr: *const u8) -> Self;
+
+    /// Writes `self` at `ptr`, which need not be aligned.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for writes of `SIZE` bytes.
+    unsafe fn write_le(self, ptr: *mut u8);
+}
+
+macro_rules! impl_little_endian_convert {
+    ($t: ty) => {
+        impl LittleEndianConvert for $t {
+            const SIZE: usize = mem::size_of::<$t>();
+
+            fn from_le(bytes: &[u8]) -> Self {
+                let mut b = [0u8; mem::size_of::<$t>()];
+                b.copy_from_slice(bytes);
+                <$t>::from_le_bytes(b)
+            }
+
+            fn to_le(self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+
+            unsafe fn read_le(ptr: *const u8) -> Self {
+                <$t>::from_le_bytes(ptr::read_unaligned(
+                    ptr as *const [u8; mem::size_of::<$t>()],
+                ))
+            }
+
+            unsafe fn write_le(self, ptr: *mut u8) {
+                ptr::write_unaligned(ptr as *mut [u8; mem::size_of::<$t>()], self.to_le_bytes())
+            }
+        }
+    };
+}
+
+impl_little_endian_convert!(i8);
+impl_little_endian_convert!(u8);
+impl_little_endian_convert!(i16);
+impl_little_endian_convert!(u16);
+impl_little_endian_convert!(i32);
+impl_little_endian_convert!(u32);
+impl_little_endian_convert!(i64);
+impl_little_endian_convert!(u64);
+impl_little_endian_convert!(f32);
+impl_little_endian_convert!(f64);
+
 macro_rules! load {
-    ($name: ident, $reader: ident, $ret_type: ty) => {
+    ($name: ident, $ret_type: ty) => {
         impl MemoryRef {
             pub fn $name(&self, addr: u32) -> Result<$ret_type, RuntimeError> {
-                let m = self.0.borrow();
-                let mut cur = Cursor::new(&m.buf);
-                cur.set_position(addr as u64);
-                let v = cur.$reader::<LittleEndian>()?;
-                Ok(v)
+                self.load(u64::from(addr))
             }
         }
     };
 }
 
 macro_rules! store {
-    ($name: ident, $writer: ident, $data_type: ty) => {
+    ($name: ident, $data_type: ty) => {
         impl MemoryRef {
             pub fn $name(&self, addr: u32, data: $data_type) -> Result<(), RuntimeError> {
-                let mut m = self.0.borrow_mut();
-                let mut cur = Cursor::new(&mut m.buf);
-                cur.set_position(addr as u64);
-                &mut cur.$writer::<LittleEndian>(data)?;
-                Ok(())
+                self.store(u64::from(addr), data)
             }
         }
     };
 }
 
 impl MemoryRef {
-    pub fn new(desc: MemoryDescriptor) -> MemoryRef {
-        MemoryRef(Rc::new(RefCell::new(Memory {
-            buf: vec![0; desc.initial as usize * PAGE_SIZE],
+    /// Fails if the memory for the initial pages cannot be allocated.
+    pub fn new(desc: MemoryDescriptor) -> Result<MemoryRef, RuntimeError> {
+        Ok(MemoryRef(Rc::new(Memory {
+            buf: UnsafeCell::new(new_buffer(desc.initial as usize * PAGE_SIZE)?),
             maximum: desc.maximum,
         })))
     }
 
+    fn with_buf<R>(&self, f: impl FnOnce(&mut Buffer) -> R) -> R {
+        // SAFETY: `Memory` is neither `Send` nor `Sync` behind an `Rc`, and `f`
+        // is always a local closure that does not reach this memory again,
+        // so this is the only live reference to the buffer.
+        f(unsafe { &mut *self.0.buf.get() })
+    }
+
+    /// Reads a `T` at `addr`. This is the only bounds check of a load.
+    #[inline]
+    pub fn load<T: LittleEndianConvert>(&self, addr: u64) -> Result<T, RuntimeError> {
+        self.with_buf(|buf| {
+            let bytes = usize::try_from(addr)
+                .ok()
+                .and_then(|start| buf.get(start..start.checked_add(T::SIZE)?))
+                .ok_or(TrapCode::OutOfBoundsMemoryAccess)?;
+            Ok(T::from_le(bytes))
+        })
+    }
+
+    /// Writes `v` at `addr`. This is the only bounds check of a store.
+    #[inline]
+    pub fn store<T: LittleEndianConvert>(&self, addr: u64, v: T) -> Result<(), RuntimeError> {
+        self.with_buf(|buf| {
+            let bytes = usize::try_from(addr)
+                .ok()
+                .and_then(|start| buf.get_mut(start..start.checked_add(T::SIZE)?))
+                .ok_or(TrapCode::OutOfBoundsMemoryAccess)?;
+            v.to_le(bytes);
+            Ok(())
+        })
+    }
+
+    /// Reads a `T` for a load instruction.
+    #[cfg(not(guard_pages))]
+    #[inline]
+    pub(crate) fn guest_load<T: LittleEndianConvert>(&self, addr: u64) -> Result<T, RuntimeError> {
+        self.load(addr)
+    }
+
+    /// Writes `v` for a store instruction.
+    #[cfg(not(guard_pages))]
+    #[inline]
+    pub(crate) fn guest_store<T: LittleEndianConvert>(
+        &self,
+        addr: u64,
+        v: T,
+    ) -> Result<(), RuntimeError> {
+        self.store(addr, v)
+    }
+
+    /// Reads a `T` for a load instruction without a bounds check. Any effective
+    /// address lies inside the reservation of the buffer, and past its length
+    /// the access faults and is turned into a trap by `catch_traps`.
+    #[cfg(guard_pages)]
+    #[inline]
+    pub(crate) fn guest_load<T: LittleEndianConvert + Copy>(
+        &self,
+        addr: u64,
+    ) -> Result<T, RuntimeError> {
+        let offset = guarded_offset::<T>(addr)?;
+        let ptr = self.with_buf(|buf| buf.as_ptr()).wrapping_add(offset);
+        catch_traps(|| unsafe { T::read_le(ptr) })
+    }
+
+    /// Writes `v` for a store instruction without a bounds check, see `guest_load`.
+    #[cfg(guard_pages)]
+    #[inline]
+    pub(crate) fn guest_store<T: LittleEndianConvert + Copy>(
+        &self,
+        addr: u64,
+        v: T,
+    ) -> Result<(), RuntimeError> {
+        let offset = guarded_offset::<T>(addr)?;
+        let ptr = self.with_buf(|buf| buf.as_mut_ptr()).wrapping_add(offset);
+        catch_traps(|| unsafe { v.write_le(ptr) })
+    }
+
     pub fn to_string(&self, start: usize, len: usize) -> Result<String, std::str::Utf8Error> {
-        let b = self.0.borrow();
-        let b = &b.buf[start..start + len];
-        let s = std::str::from_utf8(b)?;
-        Ok(s.to_owned())
+        self.with_buf(|buf| {
+            let s = std::str::from_utf8(&buf[start..start + len])?;
+            Ok(s.to_owned())
+        })
     }
 
     pub fn slice(&self, start: usize, len: usize) -> Result<Vec<u8>, std::str::Utf8Error> {
-        let b = self.0.borrow();
-        let b = &b.buf[start..start + len];
-        let s = Vec::from(b);
-        Ok(s)
+        self.with_buf(|buf| Ok(Vec::from(&buf[start..start + len])))
     }
 
     pub fn grow(&self, delta: u32) -> i32 {
-        let mut m = self.0.borrow_mut();
-        let current = m.buf.len() / PAGE_SIZE;
-
-        if let Some(max) = m.maximum {
-            if current + delta as usize > max as usize {
+        let maximum = self.0.maximum;
+        self.with_buf(|buf| {
+            let current = buf.len() / PAGE_SIZE;
+            let max = maximum.map_or(MAX_PAGES, |max| max as usize);
+            if current + delta as usize > max {
                 return -1;
             }
-        }
-        let len = m.buf.len() + (delta as usize * PAGE_SIZE);
-        m.buf.resize(len, 0);
-        current as i32
+            let len = buf.len() + (delta as usize * PAGE_SIZE);
+            if !grow_buffer(buf, len) {
+                return -1;
+            }
+            current as i32
+        })
     }
 
     pub fn current(&self) -> usize {
-        let m = self.0.borrow();
-        m.buf.len() / PAGE_SIZE
+        self.with_buf(|buf| buf.len() / PAGE_SIZE)
     }
 
     pub fn set(&self, data: &[u8], offset: usize) -> Result<(), RuntimeError> {
-        let mut m = self.0.borrow_mut();
-        let mut cur = Cursor::new(&mut m.buf);
-        cur.set_position(offset as u64);
-        for d in data {
-            let b = [*d as u8; 1];
-            cur.write_all(&b)?;
-        }
-        Ok(())
+        self.with_buf(|buf| {
+            let dst = buf
+                .get_mut(offset..offset + data.len())
+                .ok_or(TrapCode::OutOfBoundsMemoryAccess)?;
+            dst.copy_from_slice(data);
+            Ok(())
+        })
     }
 
-    pub fn i8_load(&self, addr: u32) -> Result<i8, RuntimeError> {
-        let m = self.0.borrow();
-        let mut cur = Cursor::new(&m.buf);
-        cur.set_position(u64::from(addr));
-        let mut b = [0u8; 1];
-        cur.read_exact(&mut b)?;
-        Ok(b[0] as i8)
+    /// Sets `len` bytes from `offset` to `value`, or traps without writing
+    /// anything if they are not all in bounds.
+    pub fn fill(&self, offset: usize, value: u8, len: usize) -> Result<(), RuntimeError> {
+        self.with_buf(|buf| {
+            let dst = buf
+                .get_mut(offset..offset + len)
+                .ok_or(TrapCode::OutOfBoundsMemoryAccess)?;
+            dst.iter_mut().for_each(|b| *b = value);
+            Ok(())
+        })
     }
 
-    pub fn i8_store(&self, addr: u32, data: i8) -> Result<(), RuntimeError> {
-        let mut m = self.0.borrow_mut();
-        let mut cur = Cursor::new(&mut m.buf);
-        cur.set_position(u64::from(addr));
-        let b = [data as u8; 1];
-        cur.write_all(&b)?;
-        Ok(())
-    }
-
-    pub fn u8_store(&self, addr: u32, data: u8) -> Result<(), RuntimeError> {
-        let mut m = self.0.borrow_mut();
-        let mut cur = Cursor::new(&mut m.buf);
-        cur.set_position(u64::from(addr));
-        let b = [data; 1];
-        cur.write_all(&b)?;
-        Ok(())
+    /// Copies `len` bytes from `src` to `dst`, where the ranges may overlap.
+    pub fn copy_within(&self, src: usize, dst: usize, len: usize) -> Result<(), RuntimeError> {
+        self.with_buf(|buf| {
+            if src + len > buf.len() || dst + len > buf.len() {
+                return Err(TrapCode::OutOfBoundsMemoryAccess.into());
+            }
+            buf.copy_within(src..src + len, dst);
+            Ok(())
+        })
     }
 }
 
-load!(i16_load, read_i16, i16);
-load!(i32_load, read_i32, i32);
-load!(i64_load, read_i64, i64);
-load!(f32_load, read_f32, f32);
-load!(f64_load, read_f64, f64);
+load!(i8_load, i8);
+load!(i16_load, i16);
+load!(i32_load, i32);
+load!(i64_load, i64);
+load!(f32_load, f32);
+load!(f64_load, f64);
 
-store!(i32_store, write_i32, i32);
-store!(u32_store, write_u32, u32);
-store!(i64_store, write_i64, i64);
-store!(f32_store, write_f32, f32);
-store!(f64_store, write_f64, f64);
-store!(i16_store, write_i16, i16);
+store!(i8_store, i8);
+store!(u8_store, u8);
+store!(i16_store, i16);
+store!(i32_store, i32);
+store!(u32_store, u32);
+store!(i64_store, i64);
+store!(f32_store, f32);
+store!(f64_store, f64);
 
 #[derive(Debug)]
 pub struct Memories(Vec<MemoryRef>);
@@ -163,42 +346,51 @@ impl Memories {
     pub fn from_section<'a>(
         sections: &Sections,
         globals: &Globals,
+        functions: &Functions,
         imports: Option<&'a dyn ImportResolver>,
     ) -> Result<Self, RuntimeError> {
         let mut memories = vec![];
-        let mut segments = &vec![];
-        if let Some(data) = sections.data_section.as_ref() {
-            segments = &data.segments
-        }
-
         if let Some(import_section) = sections.import_section.as_ref() {
             if let Some(imports) = &imports {
                 for entry in &import_section.entries {
                     if let ImportType::Memory(_m) = &entry.import_type {
                         let memory_ref = imports
                             .resolve_memory(entry.module_name.clone(), entry.field_name.clone())?;
-                        // For now support only one memory
-                        for s in segments {
-                            memory_ref.set(&s.data, s.offset.eval(globals)?.into())?;
-                        }
                         memories.push(memory_ref.clone());
                     }
                 }
             }
         }
 
-        if let Some(m) = sections.memory_section.as_ref() {
-            let m = &m.entries[0];
-            let m = MemoryRef::new(MemoryDescriptor::new(m.limits.initial, m.limits.maximum));
-            // For now support only one memory
-            for s in segments {
-                m.set(&s.data, s.offset.eval(globals)?.into())?;
+        if let Some(section) = sections.memory_section.as_ref() {
+            for m in &section.entries {
+                memories.push(MemoryRef::new(MemoryDescriptor::new(
+                    m.limits.initial,
+                    m.limits.maximum,
+                ))?)
+            }
+        }
+
+        // Active segments are copied in order, so the ones before a segment
+        // out of bounds stay written. Passive segments wait for `memory.init`.
+        if let Some(data) = sections.data_section.as_ref() {
+            for s in &data.segments {
+                if let SegmentMode::Active { index, offset } = &s.mode {
+                    let memory_ref = memories
+                        .get(*index as usize)
+                        .ok_or(RuntimeError::UndefinedMemoryError)?;
+                    let offset = offset.eval_offset(globals, functions)? as usize;
+                    memory_ref.set(&s.data, offset)?;
+                }
             }
-            memories.push(m)
         }
         Ok(Self(memories))
     }
 
+    pub fn get_ref(&self, index: usize) -> Option<&MemoryRef> {
+        self.0.get(index)
+    }
+
     pub fn get(&self, index: usize) -> Result<MemoryRef, RuntimeError> {
         let m = self
             .0
@@ -207,3 +399,21 @@ impl Memories {
         Ok(m.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn trap_on_address_past_memory() {
+        let memory = MemoryRef::new(MemoryDescriptor::new(1, None)).unwrap();
+        for addr in &[PAGE_SIZE as u64 - 2, 0x1_0000_0010, u64::MAX - 1] {
+            assert!(memory.load::<i32>(*addr).is_err());
+            assert!(memory.store::<i32>(*addr, 0).is_err());
+            assert!(memory.guest_load::<i32>(*addr).is_err());
+            assert!(memory.guest_store::<i32>(*addr, 0).is_err());
+        }
+        assert!(memory.load::<i32>(PAGE_SIZE as u64 - 4).is_ok());
+    }
+}
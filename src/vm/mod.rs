@@ -1,13 +1,21 @@
 pub mod error;
 pub mod exports;
+pub mod fuel;
 pub mod function;
 pub mod global;
 pub mod imports;
+pub mod interrupt;
+pub mod invocation;
 pub mod memory;
+pub mod module;
 pub mod table;
+pub mod trap;
 
 pub(crate) mod call_stack;
+#[cfg(guard_pages)]
+pub(crate) mod guard;
 pub(crate) mod instructions;
+pub(crate) mod ir;
 pub(crate) mod label;
 pub(crate) mod value_stack;
 
@@ -15,69 +23,207 @@ use crate::decoder::*;
 use crate::error::*;
 use crate::types::*;
 
+pub use instructions::opecode::Opcode;
 pub(crate) use instructions::*;
 
 pub use error::*;
 pub use exports::*;
+pub use fuel::CostTable;
 pub use function::*;
 pub use global::*;
 pub use imports::*;
+pub use interrupt::InterruptHandle;
+pub use invocation::{Invocation, InvocationResult, Suspension};
 pub use memory::*;
+pub use module::{ExportDescriptor, ExternType, ImportDescriptor, Module};
 pub use table::*;
+pub use trap::*;
 pub use value_stack::*;
 
+use std::cell::Cell;
 use std::rc::Rc;
 
 use call_stack::{CallStack, StackFrame};
+use fuel::Fuel;
+use ir::Imm;
 use label::*;
 
+/// Default maximum number of nested function calls.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 64 * 1024;
+/// Default maximum number of values on the value stack.
+pub const DEFAULT_MAX_VALUE_STACK_SIZE: usize = 1024 * 1024;
+
 enum Next {
     None,
     Continue,
+    Suspend(Suspension),
 }
 
 #[derive(Debug)]
 pub struct VM<'a> {
+    module: Module,
     exports: Exports,
     functions: Functions,
     globals: Globals,
-    table: TableRef,
+    tables: Tables,
     memories: Memories,
-    func_types: Vec<FuncType>,
+    /// Whether each data segment was dropped, which active segments are after instantiation.
+    dropped_data: Vec<Cell<bool>>,
+    /// Whether each element segment was dropped, which active and declarative
+    /// segments are after instantiation.
+    dropped_elems: Vec<Cell<bool>>,
     func_resolver: Option<&'a dyn FunctionResolver>,
+    start: Option<u32>,
+    max_call_depth: usize,
+    max_value_stack_size: usize,
+    fuel: Fuel,
+    interrupt: InterruptHandle,
 }
 
 impl<'a> VM<'a> {
-    pub fn from_section(
-        sections: Sections,
+    pub(crate) fn instantiate(
+        module: &Module,
         imports: Option<&'a dyn ImportResolver>,
     ) -> Result<Self, RuntimeError> {
+        let sections = module.sections();
         let func_resolver = VM::extract_func_resolver(sections.import_section.as_ref(), imports)?;
         let exports = Exports::from_section(sections.export_section.as_ref());
-        let globals = Globals::from_section(&sections, imports)?;
-        let functions = Functions::from_section(&sections)?;
-        let memories = Memories::from_section(&sections, &globals, imports)?;
-        let tables = Tables::from_section(&sections, &globals, &functions, imports)?;
-        // Now only one table is supported.
-        let table = tables.get_ref(0)?;
-        let func_types = sections.type_section.unwrap_or_default().entries;
+        let functions = Functions::from_section(sections, module.functions())?;
+        let globals = Globals::from_section(sections, &functions, imports)?;
+        // Element segments are written before data segments.
+        let tables = Tables::from_section(sections, &globals, &functions, imports)?;
+        let memories = Memories::from_section(sections, &globals, &functions, imports)?;
+        let dropped_data = sections
+            .data_section
+            .as_ref()
+            .map(|d| &d.segments[..])
+            .unwrap_or_default()
+            .iter()
+            .map(|s| Cell::new(s.mode != SegmentMode::Passive))
+            .collect();
+        let dropped_elems = sections
+            .element_section
+            .as_ref()
+            .map(|e| &e.entries[..])
+            .unwrap_or_default()
+            .iter()
+            .map(|s| Cell::new(s.mode != SegmentMode::Passive))
+            .collect();
+        let start = sections.start_section.as_ref().map(|s| s.index);
         Ok(Self {
+            module: module.clone(),
             exports,
             globals,
-            table,
+            tables,
             functions,
             memories,
-            func_types,
+            dropped_data,
+            dropped_elems,
             func_resolver,
+            start,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_value_stack_size: DEFAULT_MAX_VALUE_STACK_SIZE,
+            fuel: Fuel::default(),
+            interrupt: InterruptHandle::default(),
         })
     }
 
+    /// The module this VM was instantiated from.
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Sets the maximum number of nested calls. Exceeding it traps with `TrapCode::StackOverflow`.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    /// Sets the maximum number of values on the value stack, locals included.
+    /// Exceeding it traps with `TrapCode::StackOverflow`.
+    pub fn set_max_value_stack_size(&mut self, size: usize) {
+        self.max_value_stack_size = size;
+    }
+
+    /// Enables fuel metering with the given budget.
+    /// Running out of fuel traps with `TrapCode::OutOfFuel`.
+    pub fn set_fuel(&self, fuel: u64) {
+        self.fuel.set(fuel);
+    }
+
+    /// Tops up the remaining fuel, enabling metering if it was disabled.
+    pub fn add_fuel(&self, fuel: u64) {
+        self.fuel.add(fuel);
+    }
+
+    /// Returns the remaining fuel, or `None` if metering is disabled.
+    pub fn remaining_fuel(&self) -> Option<u64> {
+        self.fuel.remaining()
+    }
+
+    /// Returns a handle that aborts running invocations of this VM.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Sets how much fuel each `Opcode` consumes.
+    pub fn set_cost_table(&mut self, costs: CostTable) {
+        self.fuel.set_cost_table(costs);
+    }
+
+    /// Runs the start function of the module, if it has one.
+    /// `instantiate` calls this after memories, tables and globals are initialized.
+    pub fn start(&self) -> Result<(), YawError> {
+        let index = match self.start {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        let func = self.functions.get_ref(index as usize)?;
+        match &*func {
+            FunctionInstance::InternalFunction(func) => {
+                self.invoke_internal(func, &[])?;
+            }
+            FunctionInstance::ExternalFunction(func) => {
+                self.invoke_external(func, &[])?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn invoke(&self, name: &str, args: &[RuntimeValue]) -> Result<Vec<RuntimeValue>, YawError> {
         let index = self.exports.resolve(name)?;
+        self.invoke_function(index, args)
+    }
+
+    /// Calls the function at `index` of the function index space, exported or not.
+    /// `Module::names` maps function names to indices.
+    pub fn invoke_function(
+        &self,
+        index: u32,
+        args: &[RuntimeValue],
+    ) -> Result<Vec<RuntimeValue>, YawError> {
         let func = self.functions.get_ref(index as usize)?;
         match &*func {
             FunctionInstance::InternalFunction(func) => self.invoke_internal(func, args),
-            FunctionInstance::ExternalFunction(_) => unreachable!(),
+            FunctionInstance::ExternalFunction(func) => self.invoke_external(func, args),
+        }
+    }
+
+    /// Prepares a call of the exported function `name` that can be suspended when
+    /// it runs out of fuel or a host function yields. Nothing runs until
+    /// `Invocation::resume` is called.
+    pub fn invoke_resumable(
+        &self,
+        name: &str,
+        args: &[RuntimeValue],
+    ) -> Result<Invocation<'_, 'a>, YawError> {
+        let index = self.exports.resolve(name)?;
+        let func = self.functions.get_ref(index as usize)?;
+        match &*func {
+            FunctionInstance::InternalFunction(func) => Ok(Invocation::new(self, func, args)?),
+            FunctionInstance::ExternalFunction(f) => {
+                check_types(args, &f.args)?;
+                Ok(Invocation::host(self, func.clone(), args))
+            }
         }
     }
 
@@ -85,8 +231,8 @@ impl<'a> VM<'a> {
         &self.exports
     }
 
-    pub fn resolve_table(&self) -> TableRef {
-        self.table.clone()
+    pub fn resolve_table(&self, index: usize) -> Result<TableRef, RuntimeError> {
+        self.tables.get_ref(index)
     }
 
     pub fn resolve_global(&self, index: usize) -> Result<GlobalRef, RuntimeError> {
@@ -110,26 +256,17 @@ impl<'a> VM<'a> {
         func: &InternalFunction,
         args: &[RuntimeValue],
     ) -> Result<Vec<RuntimeValue>, YawError> {
-        let mut locals = vec![];
-        locals.extend(args);
-        for local in &func.locals {
-            for _ in 0..local.count {
-                locals.push(local.value_type.into());
-            }
-        }
-        let mut cstack = CallStack::new();
-        let mut vstack = ValueStack::new();
-        let lstack = LabelStack::new();
-        let current_frame = StackFrame::new(locals, lstack, Rc::clone(&func.instructions), 0);
-        cstack.push(current_frame);
+        let mut invocation = Invocation::new(self, func, args)?;
         loop {
-            match self.execute_function(&mut cstack, &mut vstack)? {
-                Next::None => break,
-                Next::Continue => {}
+            match invocation.resume()? {
+                InvocationResult::Finished(res) => return Ok(res),
+                InvocationResult::Suspended(Suspension::OutOfFuel) => {
+                    return Err(Trap::new(TrapCode::OutOfFuel).into())
+                }
+                // Nobody can resume later, so just keep running.
+                InvocationResult::Suspended(Suspension::Yield) => {}
             }
         }
-        let res = vstack.take_buf();
-        Ok(res)
     }
 
     fn extract_func_resolver(
@@ -154,251 +291,589 @@ impl<'a> VM<'a> {
         &self,
         cstack: &mut CallStack,
         vstack: &mut ValueStack,
+        lstack: &mut LabelStack,
+    ) -> Result<Next, YawError> {
+        let mut frame = cstack.pop().ok_or(RuntimeError::StackPopError)?;
+        self.run_frame(&mut frame, cstack, vstack, lstack)
+            .map_err(|e| self.attach_backtrace(e, &frame, cstack))
+    }
+
+    /// Records where a trap happened, from the trapping frame out to the caller
+    /// of the invocation.
+    fn attach_backtrace(
+        &self,
+        error: YawError,
+        frame: &StackFrame,
+        cstack: &CallStack,
+    ) -> YawError {
+        match error {
+            // A trap of a nested instance already has its own backtrace.
+            YawError::Trap { trap } if trap.backtrace().is_empty() => {
+                let names = self.module.names();
+                let lines = self.module.line_table();
+                let backtrace = std::iter::once(frame)
+                    .chain(cstack.frames().rev())
+                    .map(|frame| {
+                        // `pc` is past the instruction that trapped or called the next frame.
+                        let pc = frame.pc.saturating_sub(1);
+                        let func_index = frame.code.func_index;
+                        let code_offset = frame.code.offset(pc);
+                        FrameInfo {
+                            func_index,
+                            func_name: names.function_name(func_index).map(|s| s.to_owned()),
+                            pc,
+                            code_offset,
+                            location: code_offset.and_then(|offset| lines.find(offset)),
+                        }
+                    })
+                    .collect();
+                trap.with_backtrace(backtrace).into()
+            }
+            error => error,
+        }
+    }
+
+    /// Runs `frame` until it calls, returns or suspends, keeping its `pc` up to date
+    /// so that a trap can be located.
+    fn run_frame(
+        &self,
+        frame: &mut StackFrame,
+        cstack: &mut CallStack,
+        vstack: &mut ValueStack,
+        lstack: &mut LabelStack,
     ) -> Result<Next, YawError> {
-        let StackFrame {
-            instructions,
-            mut locals,
-            mut lstack,
-            mut pc,
-        } = cstack.pop().ok_or(RuntimeError::StackPopError)?;
+        let (base, label_base, arity) = (frame.base, frame.label_base, frame.arity);
+        let code = &frame.code;
+        let pc = &mut frame.pc;
+        if *pc == 0 {
+            self.check_interrupt()?;
+        }
         loop {
-            if instructions.len() == pc {
-                if cstack.len() > 0 {
-                    return Ok(Next::Continue);
-                }
-                return Ok(Next::None);
+            if code.len() == *pc {
+                return Ok(return_from(
+                    cstack, vstack, lstack, base, label_base, arity,
+                )?);
             }
-            let inst = &instructions[pc];
-            pc += 1;
-            match inst.0 {
-                Opcode::Unreachable => panic!("unreachable"),
-                Opcode::Select => select(vstack)?,
+            let inst = code.instrs[*pc];
+            if !self.fuel.consume(inst.opcode) {
+                let frame = StackFrame::new(Rc::clone(code), *pc, base, label_base, arity);
+                cstack.push(frame);
+                return Ok(Next::Suspend(Suspension::OutOfFuel));
+            }
+            *pc += 1;
+            match inst.opcode {
+                Opcode::Unreachable => return Err(Trap::new(TrapCode::Unreachable).into()),
+                Opcode::Select | Opcode::TypedSelect => select(vstack)?,
                 Opcode::Drop => drop(vstack)?,
                 Opcode::Call => {
-                    let index: usize = inst.1[0].into();
-                    let func = self.functions.get_ref(index)?;
+                    let func = self.functions.get_ref(inst.imm.index() as usize)?;
                     match &*func {
                         FunctionInstance::InternalFunction(func) => {
-                            let instrs = Rc::clone(&instructions);
-                            let frame = StackFrame::new(locals, lstack, instrs, pc);
+                            self.check_stack_limits(cstack, vstack)?;
+                            let frame =
+                                StackFrame::new(Rc::clone(code), *pc, base, label_base, arity);
                             // Save current context
                             cstack.push(frame);
-                            cstack.push(self.create_new_frame(func, vstack)?);
+                            cstack.push(self.create_new_frame(func, vstack, lstack)?);
                             return Ok(Next::Continue);
                         }
                         FunctionInstance::ExternalFunction(func) => {
-                            self.execute_external_function(func, vstack)?
+                            if self.execute_external_function(func, vstack)? {
+                                let frame =
+                                    StackFrame::new(Rc::clone(code), *pc, base, label_base, arity);
+                                cstack.push(frame);
+                                return Ok(Next::Suspend(Suspension::Yield));
+                            }
                         }
                     }
                 }
                 Opcode::CallIndirect => {
-                    let type_index: usize = inst.1[0].into();
-                    // Reserved
-                    let entry_index: usize = pop(vstack)?.into();
-                    if let Some(fn_ref) = self.table.borrow().entries.get(entry_index) {
-                        if let Some(fn_ref) = fn_ref {
-                            let func = Rc::clone(fn_ref);
-                            match &*func {
-                                FunctionInstance::InternalFunction(func) => {
-                                    self.validate_call_indirect(func, type_index)?;
-                                    let instrs = Rc::clone(&instructions);
-                                    let frame = StackFrame::new(locals, lstack, instrs, pc);
-                                    // Save current context
-                                    cstack.push(frame);
-                                    cstack.push(self.create_new_frame(func, vstack)?);
-                                    return Ok(Next::Continue);
-                                }
-                                FunctionInstance::ExternalFunction(func) => {
-                                    self.execute_external_function(func, vstack)?
-                                }
+                    let (type_index, table_index) = inst.imm.indices();
+                    let entry_index = pop_u32(vstack)? as usize;
+                    let func = match self.table(table_index)?.borrow().entries.get(entry_index) {
+                        Some(RuntimeValue::FuncRef(Some(func))) => Rc::clone(func.function()),
+                        Some(_) => return Err(Trap::new(TrapCode::UninitializedElement).into()),
+                        None => return Err(Trap::new(TrapCode::UndefinedElement).into()),
+                    };
+                    match &*func {
+                        FunctionInstance::InternalFunction(func) => {
+                            self.validate_call_indirect(
+                                &func.args,
+                                &func.results,
+                                type_index as usize,
+                            )?;
+                            self.check_stack_limits(cstack, vstack)?;
+                            let frame =
+                                StackFrame::new(Rc::clone(code), *pc, base, label_base, arity);
+                            // Save current context
+                            cstack.push(frame);
+                            cstack.push(self.create_new_frame(func, vstack, lstack)?);
+                            return Ok(Next::Continue);
+                        }
+                        FunctionInstance::ExternalFunction(func) => {
+                            self.validate_call_indirect(
+                                &func.args,
+                                &func.results,
+                                type_index as usize,
+                            )?;
+                            if self.execute_external_function(func, vstack)? {
+                                let frame =
+                                    StackFrame::new(Rc::clone(code), *pc, base, label_base, arity);
+                                cstack.push(frame);
+                                return Ok(Next::Suspend(Suspension::Yield));
                             }
-                        } else {
-                            return Err(RuntimeError::UnInitializedElementError.into());
                         }
-                    } else {
-                        return Err(RuntimeError::UndefinedElementError.into());
                     }
                 }
-                Opcode::If => pc = r#if(&inst.1, &instructions, pc, vstack, &mut lstack)?,
-                Opcode::Else => pc = r#else(&instructions, pc, &mut lstack)?,
+                Opcode::If => {
+                    if let Imm::If {
+                        params,
+                        results,
+                        els,
+                        end,
+                    } = inst.imm
+                    {
+                        *pc = r#if(params, results, els, end, *pc, vstack, lstack)?
+                    }
+                }
+                Opcode::Else => *pc = r#else(lstack)?,
                 Opcode::Nop => {}
-                Opcode::Loop => r#loop(&inst.1, pc, vstack, &mut lstack)?,
-                Opcode::Block => block(&inst.1, pc, vstack, &mut lstack)?,
-                Opcode::BrIf => pc = br_if(&inst.1, &instructions, pc, vstack, &mut lstack)?,
-                Opcode::Br => pc = br(&inst.1, &instructions, pc, vstack, &mut lstack)?,
-                Opcode::BrTable => pc = br_table(&inst.1, &instructions, pc, vstack, &mut lstack)?,
-                Opcode::Return => {
-                    if cstack.len() == 0 {
-                        return Ok(Next::None);
+                Opcode::Loop => {
+                    // Branches to a loop jump back to this instruction.
+                    self.check_interrupt()?;
+                    if let Imm::Loop { params } = inst.imm {
+                        r#loop(params, *pc, vstack, lstack)?
                     }
-                    return Ok(Next::Continue);
+                }
+                Opcode::Block => {
+                    if let Imm::Block {
+                        params,
+                        results,
+                        end,
+                    } = inst.imm
+                    {
+                        block(params, results, end, vstack, lstack)?
+                    }
+                }
+                Opcode::BrIf => {
+                    let depth = inst.imm.depth();
+                    *pc = br_if(depth, *pc, code.len(), label_base, vstack, lstack)?
+                }
+                Opcode::Br => {
+                    let depth = inst.imm.depth();
+                    *pc = br(depth, code.len(), label_base, vstack, lstack)?
+                }
+                Opcode::BrTable => {
+                    if let Imm::Table { start, len } = inst.imm {
+                        let depths = code.br_table(start, len);
+                        *pc = br_table(depths, code.len(), label_base, vstack, lstack)?
+                    }
+                }
+                Opcode::Return => {
+                    return Ok(return_from(
+                        cstack, vstack, lstack, base, label_base, arity,
+                    )?);
                 }
                 Opcode::End => {
-                    lstack.take_before(1);
+                    lstack.pop();
                 }
                 // Variable access
-                Opcode::GetLocal => get_local(&inst.1, vstack, &locals)?,
-                Opcode::SetLocal => set_local(&inst.1, vstack, &mut locals)?,
-                Opcode::TeeLocal => tee_local(&inst.1, vstack, &mut locals)?,
-                Opcode::GetGlobal => get_global(&inst.1, vstack, &self.globals)?,
-                Opcode::SetGlobal => set_global(&inst.1, vstack, &self.globals)?,
+                Opcode::GetLocal => get_local(inst.imm.index(), vstack, base)?,
+                Opcode::SetLocal => set_local(inst.imm.index(), vstack, base)?,
+                Opcode::TeeLocal => tee_local(inst.imm.index(), vstack, base)?,
+                Opcode::GetGlobal => get_global(inst.imm.index(), vstack, &self.globals)?,
+                Opcode::SetGlobal => set_global(inst.imm.index(), vstack, &self.globals)?,
                 // Memory related operations
-                Opcode::I32Load => i32_load(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load => i64_load(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::F32Load => f32_load(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::F64Load => f64_load(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Load8S => i32_load8_s(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Load8U => i32_load8_u(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Load16S => i32_load16_s(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Load16U => i32_load16_u(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load8S => i64_load8_s(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load8U => i64_load8_u(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load16S => i64_load16_s(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load16U => i64_load16_u(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load32S => i64_load32_s(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Load32U => i64_load32_u(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Store => i32_store(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Store => i64_store(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::F32Store => f32_store(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::F64Store => f64_store(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Store8 => i32_store8(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I32Store16 => i32_store16(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Store8 => i64_store8(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Store16 => i64_store16(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::I64Store32 => i64_store32(&inst.1, vstack, &self.resolve_memory()?)?,
-                Opcode::CurrentMemory => current(vstack, &self.resolve_memory()?)?,
-                Opcode::GrowMemory => grow(vstack, &self.resolve_memory()?)?,
+                Opcode::I32Load => i32_load(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load => i64_load(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::F32Load => f32_load(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::F64Load => f64_load(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Load8S => i32_load8_s(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Load8U => i32_load8_u(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Load16S => i32_load16_s(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Load16U => i32_load16_u(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load8S => i64_load8_s(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load8U => i64_load8_u(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load16S => i64_load16_s(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load16U => i64_load16_u(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load32S => i64_load32_s(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Load32U => i64_load32_u(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Store => i32_store(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Store => i64_store(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::F32Store => f32_store(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::F64Store => f64_store(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Store8 => i32_store8(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I32Store16 => i32_store16(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Store8 => i64_store8(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Store16 => i64_store16(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::I64Store32 => i64_store32(inst.imm.offset(), vstack, self.memory()?)?,
+                Opcode::CurrentMemory => current(vstack, self.memory()?)?,
+                Opcode::GrowMemory => grow(vstack, self.memory()?)?,
+                Opcode::MemoryInit => {
+                    let data = self.data_segment(inst.imm.index());
+                    memory_init(vstack, self.memory()?, data)?
+                }
+                Opcode::DataDrop => self.dropped_data[inst.imm.index() as usize].set(true),
+                Opcode::MemoryCopy => memory_copy(vstack, self.memory()?)?,
+                Opcode::MemoryFill => memory_fill(vstack, self.memory()?)?,
+                Opcode::TableInit => {
+                    let (segment, table) = inst.imm.indices();
+                    let elems = self.elem_segment(segment)?;
+                    table_init(vstack, self.table(table)?, &elems)?
+                }
+                Opcode::ElemDrop => self.dropped_elems[inst.imm.index() as usize].set(true),
+                Opcode::TableCopy => {
+                    let (dst, src) = inst.imm.indices();
+                    table_copy(vstack, self.table(dst)?, self.table(src)?)?
+                }
+                Opcode::TableGet => table_get(vstack, self.table(inst.imm.index())?)?,
+                Opcode::TableSet => table_set(vstack, self.table(inst.imm.index())?)?,
+                Opcode::TableSize => table_size(vstack, self.table(inst.imm.index())?)?,
+                Opcode::TableGrow => table_grow(vstack, self.table(inst.imm.index())?)?,
+                Opcode::TableFill => table_fill(vstack, self.table(inst.imm.index())?)?,
+                Opcode::RefNull => {
+                    if let Imm::RefType(t) = inst.imm {
+                        ref_null(t, vstack)?
+                    }
+                }
+                Opcode::RefIsNull => ref_is_null(vstack)?,
+                Opcode::RefFunc => {
+                    let func = self.functions.get_ref(inst.imm.index() as usize)?;
+                    ref_func(func, vstack)?
+                }
                 // Constants
-                Opcode::I32Const => i32_const(&inst.1, vstack)?,
-                Opcode::I64Const => i64_const(&inst.1, vstack)?,
-                Opcode::F32Const => f32_const(&inst.1, vstack)?,
-                Opcode::F64Const => f64_const(&inst.1, vstack)?,
+                Opcode::I32Const => {
+                    if let Imm::I32(v) = inst.imm {
+                        i32_const(v, vstack)?
+                    }
+                }
+                Opcode::I64Const => {
+                    if let Imm::I64(v) = inst.imm {
+                        i64_const(v, vstack)?
+                    }
+                }
+                Opcode::F32Const => {
+                    if let Imm::F32(v) = inst.imm {
+                        f32_const(v, vstack)?
+                    }
+                }
+                Opcode::F64Const => {
+                    if let Imm::F64(v) = inst.imm {
+                        f64_const(v, vstack)?
+                    }
+                }
                 // Comparison operations
-                Opcode::I32Eqz | Opcode::I64Eqz => eqz(vstack)?,
-                Opcode::I32Eq | Opcode::I64Eq | Opcode::F32Eq | Opcode::F64Eq => eq(vstack)?,
-                Opcode::I32Ne | Opcode::I64Ne | Opcode::F32Ne | Opcode::F64Ne => ne(vstack)?,
-                Opcode::I32LtS | Opcode::I64LtS | Opcode::F32Lt | Opcode::F64Lt => lt_s(vstack)?,
-                Opcode::I32LtU | Opcode::I64LtU => lt_u(vstack)?,
-                Opcode::I32GtS | Opcode::I64GtS | Opcode::F32Gt | Opcode::F64Gt => gt_s(vstack)?,
-                Opcode::I32GtU | Opcode::I64GtU => gt_u(vstack)?,
-                Opcode::I32LeS | Opcode::I64LeS | Opcode::F32Le | Opcode::F64Le => le_s(vstack)?,
-                Opcode::I32LeU | Opcode::I64LeU => le_u(vstack)?,
-                Opcode::I32GeU | Opcode::I64GeU => ge_u(vstack)?,
-                Opcode::I32GeS | Opcode::I64GeS | Opcode::F32Ge | Opcode::F64Ge => ge_s(vstack)?,
+                Opcode::I32Eqz => i32_eqz(vstack)?,
+                Opcode::I32Eq => i32_eq(vstack)?,
+                Opcode::I32Ne => i32_ne(vstack)?,
+                Opcode::I32LtS => i32_lt_s(vstack)?,
+                Opcode::I32LtU => i32_lt_u(vstack)?,
+                Opcode::I32GtS => i32_gt_s(vstack)?,
+                Opcode::I32GtU => i32_gt_u(vstack)?,
+                Opcode::I32LeS => i32_le_s(vstack)?,
+                Opcode::I32LeU => i32_le_u(vstack)?,
+                Opcode::I32GeS => i32_ge_s(vstack)?,
+                Opcode::I32GeU => i32_ge_u(vstack)?,
+                Opcode::I64Eqz => i64_eqz(vstack)?,
+                Opcode::I64Eq => i64_eq(vstack)?,
+                Opcode::I64Ne => i64_ne(vstack)?,
+                Opcode::I64LtS => i64_lt_s(vstack)?,
+                Opcode::I64LtU => i64_lt_u(vstack)?,
+                Opcode::I64GtS => i64_gt_s(vstack)?,
+                Opcode::I64GtU => i64_gt_u(vstack)?,
+                Opcode::I64LeS => i64_le_s(vstack)?,
+                Opcode::I64LeU => i64_le_u(vstack)?,
+                Opcode::I64GeS => i64_ge_s(vstack)?,
+                Opcode::I64GeU => i64_ge_u(vstack)?,
+                Opcode::F32Eq => f32_eq(vstack)?,
+                Opcode::F32Ne => f32_ne(vstack)?,
+                Opcode::F32Lt => f32_lt(vstack)?,
+                Opcode::F32Gt => f32_gt(vstack)?,
+                Opcode::F32Le => f32_le(vstack)?,
+                Opcode::F32Ge => f32_ge(vstack)?,
+                Opcode::F64Eq => f64_eq(vstack)?,
+                Opcode::F64Ne => f64_ne(vstack)?,
+                Opcode::F64Lt => f64_lt(vstack)?,
+                Opcode::F64Gt => f64_gt(vstack)?,
+                Opcode::F64Le => f64_le(vstack)?,
+                Opcode::F64Ge => f64_ge(vstack)?,
                 // Numeric operations
-                Opcode::I32Clz | Opcode::I64Clz => clz(vstack)?,
-                Opcode::I32Ctz | Opcode::I64Ctz => ctz(vstack)?,
-                Opcode::I32Popcnt | Opcode::I64Popcnt => popcnt(vstack)?,
-                Opcode::I32Add | Opcode::I64Add | Opcode::F32Add | Opcode::F64Add => add(vstack)?,
-                Opcode::I32Sub | Opcode::I64Sub | Opcode::F32Sub | Opcode::F64Sub => sub(vstack)?,
-                Opcode::I32Mul | Opcode::I64Mul | Opcode::F32Mul | Opcode::F64Mul => mul(vstack)?,
-                Opcode::I32DivS | Opcode::I64DivS => div_s(vstack)?,
-                Opcode::I32DivU | Opcode::I64DivU => div_u(vstack)?,
-                Opcode::I32RemS | Opcode::I64RemS => rem_s(vstack)?,
-                Opcode::I32RemU | Opcode::I64RemU => rem_u(vstack)?,
-                Opcode::I32And | Opcode::I64And => and(vstack)?,
-                Opcode::I32Or | Opcode::I64Or => or(vstack)?,
-                Opcode::I32Xor | Opcode::I64Xor => xor(vstack)?,
-                Opcode::I32Shl | Opcode::I64Shl => shl(vstack)?,
-                Opcode::I32ShrS | Opcode::I64ShrS => shr_s(vstack)?,
-                Opcode::I32ShrU | Opcode::I64ShrU => shr_u(vstack)?,
-                Opcode::I32Rotl | Opcode::I64Rotl => rotl(vstack)?,
-                Opcode::I32Rotr | Opcode::I64Rotr => rotr(vstack)?,
-                Opcode::F32Abs | Opcode::F64Abs => abs(vstack)?,
-                Opcode::F32Neg | Opcode::F64Neg => neg(vstack)?,
-                Opcode::F32Ceil | Opcode::F64Ceil => ceil(vstack)?,
-                Opcode::F32Floor | Opcode::F64Floor => floor(vstack)?,
-                Opcode::F32Trunc | Opcode::F64Trunc => trunc(vstack)?,
-                Opcode::F32Nearest | Opcode::F64Nearest => nearest(vstack)?,
-                Opcode::F32Sqrt | Opcode::F64Sqrt => sqrt(vstack)?,
-                Opcode::F32Div | Opcode::F64Div => div(vstack)?,
-                Opcode::F32Min | Opcode::F64Min => min(vstack)?,
-                Opcode::F32Max | Opcode::F64Max => max(vstack)?,
-                Opcode::F32Copysign | Opcode::F64Copysign => copysign(vstack)?,
+                Opcode::I32Clz => i32_clz(vstack)?,
+                Opcode::I32Ctz => i32_ctz(vstack)?,
+                Opcode::I32Popcnt => i32_popcnt(vstack)?,
+                Opcode::I32Add => i32_add(vstack)?,
+                Opcode::I32Sub => i32_sub(vstack)?,
+                Opcode::I32Mul => i32_mul(vstack)?,
+                Opcode::I32DivS => i32_div_s(vstack)?,
+                Opcode::I32DivU => i32_div_u(vstack)?,
+                Opcode::I32RemS => i32_rem_s(vstack)?,
+                Opcode::I32RemU => i32_rem_u(vstack)?,
+                Opcode::I32And => i32_and(vstack)?,
+                Opcode::I32Or => i32_or(vstack)?,
+                Opcode::I32Xor => i32_xor(vstack)?,
+                Opcode::I32Shl => i32_shl(vstack)?,
+                Opcode::I32ShrS => i32_shr_s(vstack)?,
+                Opcode::I32ShrU => i32_shr_u(vstack)?,
+                Opcode::I32Rotl => i32_rotl(vstack)?,
+                Opcode::I32Rotr => i32_rotr(vstack)?,
+                Opcode::I64Clz => i64_clz(vstack)?,
+                Opcode::I64Ctz => i64_ctz(vstack)?,
+                Opcode::I64Popcnt => i64_popcnt(vstack)?,
+                Opcode::I64Add => i64_add(vstack)?,
+                Opcode::I64Sub => i64_sub(vstack)?,
+                Opcode::I64Mul => i64_mul(vstack)?,
+                Opcode::I64DivS => i64_div_s(vstack)?,
+                Opcode::I64DivU => i64_div_u(vstack)?,
+                Opcode::I64RemS => i64_rem_s(vstack)?,
+                Opcode::I64RemU => i64_rem_u(vstack)?,
+                Opcode::I64And => i64_and(vstack)?,
+                Opcode::I64Or => i64_or(vstack)?,
+                Opcode::I64Xor => i64_xor(vstack)?,
+                Opcode::I64Shl => i64_shl(vstack)?,
+                Opcode::I64ShrS => i64_shr_s(vstack)?,
+                Opcode::I64ShrU => i64_shr_u(vstack)?,
+                Opcode::I64Rotl => i64_rotl(vstack)?,
+                Opcode::I64Rotr => i64_rotr(vstack)?,
+                Opcode::F32Abs => f32_abs(vstack)?,
+                Opcode::F32Neg => f32_neg(vstack)?,
+                Opcode::F32Ceil => f32_ceil(vstack)?,
+                Opcode::F32Floor => f32_floor(vstack)?,
+                Opcode::F32Trunc => f32_trunc(vstack)?,
+                Opcode::F32Nearest => f32_nearest(vstack)?,
+                Opcode::F32Sqrt => f32_sqrt(vstack)?,
+                Opcode::F32Add => f32_add(vstack)?,
+                Opcode::F32Sub => f32_sub(vstack)?,
+                Opcode::F32Mul => f32_mul(vstack)?,
+                Opcode::F32Div => f32_div(vstack)?,
+                Opcode::F32Min => f32_min(vstack)?,
+                Opcode::F32Max => f32_max(vstack)?,
+                Opcode::F32Copysign => f32_copysign(vstack)?,
+                Opcode::F64Abs => f64_abs(vstack)?,
+                Opcode::F64Neg => f64_neg(vstack)?,
+                Opcode::F64Ceil => f64_ceil(vstack)?,
+                Opcode::F64Floor => f64_floor(vstack)?,
+                Opcode::F64Trunc => f64_trunc(vstack)?,
+                Opcode::F64Nearest => f64_nearest(vstack)?,
+                Opcode::F64Sqrt => f64_sqrt(vstack)?,
+                Opcode::F64Add => f64_add(vstack)?,
+                Opcode::F64Sub => f64_sub(vstack)?,
+                Opcode::F64Mul => f64_mul(vstack)?,
+                Opcode::F64Div => f64_div(vstack)?,
+                Opcode::F64Min => f64_min(vstack)?,
+                Opcode::F64Max => f64_max(vstack)?,
+                Opcode::F64Copysign => f64_copysign(vstack)?,
                 // Conversions
-                Opcode::I32WrapI64 => wrap(vstack)?,
-                Opcode::I32TruncSF32 | Opcode::I32TruncSF64 => trunc_s_toi32(vstack)?,
-                Opcode::I64TruncSF32 | Opcode::I64TruncSF64 => trunc_s_toi64(vstack)?,
-                Opcode::I32TruncUF32 | Opcode::I32TruncUF64 => trunc_u_toi32(vstack)?,
-                Opcode::I64TruncUF32 | Opcode::I64TruncUF64 => trunc_u_toi64(vstack)?,
-                Opcode::I64ExtendSI32 => extend_s(vstack)?,
-                Opcode::I64ExtendUI32 => extend_u(vstack)?,
-                Opcode::F32ConvertSI32 | Opcode::F32ConvertSI64 => convert_s_tof32(vstack)?,
-                Opcode::F32ConvertUI32 | Opcode::F32ConvertUI64 => convert_u_tof32(vstack)?,
-                Opcode::F64ConvertSI32 | Opcode::F64ConvertSI64 => convert_s_tof64(vstack)?,
-                Opcode::F64ConvertUI32 | Opcode::F64ConvertUI64 => convert_u_tof64(vstack)?,
-                Opcode::F32DemoteF64 => demote(vstack)?,
-                Opcode::F64PromoteF32 => promote(vstack)?,
-                // Reinterpretations
-                Opcode::F32ReinterpretI32
-                | Opcode::F64ReinterpretI64
-                | Opcode::I32ReinterpretF32
-                | Opcode::I64ReinterpretF64 => reinterpret(vstack)?,
+                Opcode::I32WrapI64 => i32_wrap_i64(vstack)?,
+                Opcode::I32TruncSF32 => i32_trunc_s_f32(vstack)?,
+                Opcode::I32TruncUF32 => i32_trunc_u_f32(vstack)?,
+                Opcode::I32TruncSF64 => i32_trunc_s_f64(vstack)?,
+                Opcode::I32TruncUF64 => i32_trunc_u_f64(vstack)?,
+                Opcode::I64ExtendSI32 => i64_extend_s_i32(vstack)?,
+                Opcode::I64ExtendUI32 => i64_extend_u_i32(vstack)?,
+                Opcode::I64TruncSF32 => i64_trunc_s_f32(vstack)?,
+                Opcode::I64TruncUF32 => i64_trunc_u_f32(vstack)?,
+                Opcode::I64TruncSF64 => i64_trunc_s_f64(vstack)?,
+                Opcode::I64TruncUF64 => i64_trunc_u_f64(vstack)?,
+                Opcode::I32TruncSatSF32 => i32_trunc_sat_s_f32(vstack)?,
+                Opcode::I32TruncSatUF32 => i32_trunc_sat_u_f32(vstack)?,
+                Opcode::I32TruncSatSF64 => i32_trunc_sat_s_f64(vstack)?,
+                Opcode::I32TruncSatUF64 => i32_trunc_sat_u_f64(vstack)?,
+                Opcode::I64TruncSatSF32 => i64_trunc_sat_s_f32(vstack)?,
+                Opcode::I64TruncSatUF32 => i64_trunc_sat_u_f32(vstack)?,
+                Opcode::I64TruncSatSF64 => i64_trunc_sat_s_f64(vstack)?,
+                Opcode::I64TruncSatUF64 => i64_trunc_sat_u_f64(vstack)?,
+                Opcode::F32ConvertSI32 => f32_convert_s_i32(vstack)?,
+                Opcode::F32ConvertUI32 => f32_convert_u_i32(vstack)?,
+                Opcode::F32ConvertSI64 => f32_convert_s_i64(vstack)?,
+                Opcode::F32ConvertUI64 => f32_convert_u_i64(vstack)?,
+                Opcode::F32DemoteF64 => f32_demote_f64(vstack)?,
+                Opcode::F64ConvertSI32 => f64_convert_s_i32(vstack)?,
+                Opcode::F64ConvertUI32 => f64_convert_u_i32(vstack)?,
+                Opcode::F64ConvertSI64 => f64_convert_s_i64(vstack)?,
+                Opcode::F64ConvertUI64 => f64_convert_u_i64(vstack)?,
+                Opcode::F64PromoteF32 => f64_promote_f32(vstack)?,
+                Opcode::I32ReinterpretF32 => i32_reinterpret_f32(vstack)?,
+                Opcode::I64ReinterpretF64 => i64_reinterpret_f64(vstack)?,
+                Opcode::F32ReinterpretI32 => f32_reinterpret_i32(vstack)?,
+                Opcode::F64ReinterpretI64 => f64_reinterpret_i64(vstack)?,
+                // Sign extensions
+                Opcode::I32Extend8S => i32_extend8_s(vstack)?,
+                Opcode::I32Extend16S => i32_extend16_s(vstack)?,
+                Opcode::I64Extend8S => i64_extend8_s(vstack)?,
+                Opcode::I64Extend16S => i64_extend16_s(vstack)?,
+                Opcode::I64Extend32S => i64_extend32_s(vstack)?,
             };
         }
     }
 
+    fn invoke_external(
+        &self,
+        func: &ExternalFunction,
+        args: &[RuntimeValue],
+    ) -> Result<Vec<RuntimeValue>, YawError> {
+        check_types(args, &func.args)?;
+        let mut vstack = ValueStack::new();
+        for arg in args {
+            vstack.push(arg.clone());
+        }
+        self.execute_external_function(func, &mut vstack)?;
+        Ok(vstack.take_buf())
+    }
+
+    /// Calls a host function and returns whether it asked to yield.
     fn execute_external_function(
         &self,
         func: &ExternalFunction,
         vstack: &mut ValueStack,
-    ) -> Result<(), RuntimeError> {
+    ) -> Result<bool, RuntimeError> {
         if let Some(resolver) = &self.func_resolver {
             let mut args: Vec<RuntimeValue> = vec![];
             for _ in 0..func.args.len() {
                 args.push(pop(vstack)?);
             }
             let result = resolver.invoke(&func.module_name, &func.field_name, &args)?;
+            // The caller relies on exactly the declared results being pushed.
+            check_types(&result, &func.results)?;
             for r in result {
                 vstack.push(r);
             }
-            return Ok(());
+            return Ok(resolver.yield_requested());
         }
         Err(RuntimeError::UndefinedFunctionError)
     }
 
+    /// Checks the signature of a function found in a table against the type
+    /// `call_indirect` expects.
     fn validate_call_indirect(
         &self,
-        func: &InternalFunction,
+        args: &[ValueType],
+        results: &[ValueType],
         type_index: usize,
     ) -> Result<(), RuntimeError> {
-        if func.args.len() != self.func_types[type_index].args.len() {
-            return Err(RuntimeError::IndirectCallTypeMismatchError);
+        let func_type = &self.module.func_types()[type_index];
+        if func_type.args != args || func_type.results != results {
+            return Err(TrapCode::IndirectCallTypeMismatch.into());
         }
-        for i in 0..func.args.len() {
-            if func.args[i] != self.func_types[type_index].args[i] {
-                return Err(RuntimeError::IndirectCallTypeMismatchError);
-            }
+        Ok(())
+    }
+
+    fn check_stack_limits(
+        &self,
+        cstack: &CallStack,
+        vstack: &ValueStack,
+    ) -> Result<(), RuntimeError> {
+        // The running frame is not on `cstack` while it executes, so a new call
+        // makes the depth `cstack.len() + 2`.
+        if cstack.len() + 2 > self.max_call_depth || vstack.len() > self.max_value_stack_size {
+            return Err(TrapCode::StackOverflow.into());
         }
+        Ok(())
+    }
 
-        if func.results.len() != self.func_types[type_index].results.len() {
-            return Err(RuntimeError::IndirectCallTypeMismatchError);
+    /// The memory used by load and store instructions, without cloning its handle.
+    #[inline]
+    fn memory(&self) -> Result<&MemoryRef, RuntimeError> {
+        self.memories
+            .get_ref(0)
+            .ok_or(RuntimeError::UndefinedMemoryError)
+    }
+
+    /// The bytes of a data segment, which are empty once it is dropped.
+    fn data_segment(&self, index: u32) -> &[u8] {
+        let index = index as usize;
+        match self.module.sections().data_section.as_ref() {
+            Some(data) if !self.dropped_data[index].get() => &data.segments[index].data,
+            _ => &[],
         }
-        for i in 0..func.results.len() {
-            if func.results[i] != self.func_types[type_index].results[i] {
-                return Err(RuntimeError::IndirectCallTypeMismatchError);
+    }
+
+    /// The references of an element segment, which are empty once it is dropped.
+    fn elem_segment(&self, index: u32) -> Result<Vec<RuntimeValue>, RuntimeError> {
+        let index = index as usize;
+        match self.module.sections().element_section.as_ref() {
+            Some(elem) if !self.dropped_elems[index].get() => {
+                Ok(elem.entries[index].eval_elems(&self.globals, &self.functions)?)
             }
+            _ => Ok(vec![]),
+        }
+    }
+
+    #[inline]
+    fn table(&self, index: u32) -> Result<&TableRef, RuntimeError> {
+        self.tables
+            .get(index as usize)
+            .ok_or(RuntimeError::UndefinedTableError)
+    }
+
+    fn check_interrupt(&self) -> Result<(), RuntimeError> {
+        if self.interrupt.take() {
+            return Err(TrapCode::Interrupted.into());
         }
         Ok(())
     }
 
-    fn create_new_frame(
+    /// Pushes the zeroed locals of `func`, which count against the value stack limit.
+    pub(crate) fn push_locals(
         &self,
         func: &InternalFunction,
         vstack: &mut ValueStack,
-    ) -> Result<StackFrame, RuntimeError> {
-        let mut locals = vec![];
-        for _ in &func.args {
-            locals.push(vstack.pop().expect("should pop"));
+    ) -> Result<(), RuntimeError> {
+        let count: usize = func.locals.iter().map(|l| l.count as usize).sum();
+        if vstack.len() + count > self.max_value_stack_size {
+            return Err(TrapCode::StackOverflow.into());
         }
-        locals.reverse();
         for local in &func.locals {
             for _ in 0..local.count {
-                locals.push(local.value_type.into());
+                vstack.push(local.value_type.into());
             }
         }
-        let lstack = LabelStack::new();
-        let frame = StackFrame::new(locals, lstack, Rc::clone(&func.instructions), 0);
-        Ok(frame)
+        Ok(())
+    }
+
+    fn create_new_frame(
+        &self,
+        func: &InternalFunction,
+        vstack: &mut ValueStack,
+        lstack: &LabelStack,
+    ) -> Result<StackFrame, RuntimeError> {
+        // The arguments are already on the value stack and become the first locals.
+        let base = vstack
+            .len()
+            .checked_sub(func.args.len())
+            .ok_or(RuntimeError::StackPopError)?;
+        self.push_locals(func, vstack)?;
+        let code = Rc::clone(&func.code);
+        let arity = func.results.len();
+        Ok(StackFrame::new(code, 0, base, lstack.len(), arity))
+    }
+}
+
+/// Checks values passed between the host and the guest against the declared types.
+pub(crate) fn check_types(
+    values: &[RuntimeValue],
+    types: &[ValueType],
+) -> Result<(), RuntimeError> {
+    if values.len() != types.len() || values.iter().zip(types).any(|(v, t)| !v.is_of_type(*t)) {
+        return Err(RuntimeError::TypeError);
+    }
+    Ok(())
+}
+
+/// Drops the locals and operands of a returning frame, keeping only its results.
+fn return_from(
+    cstack: &CallStack,
+    vstack: &mut ValueStack,
+    lstack: &mut LabelStack,
+    base: usize,
+    label_base: usize,
+    arity: usize,
+) -> Result<Next, RuntimeError> {
+    lstack.truncate(label_base);
+    vstack
+        .drop_keep(base, arity)
+        .ok_or(RuntimeError::StackPopError)?;
+    if cstack.len() > 0 {
+        return Ok(Next::Continue);
     }
+    Ok(Next::None)
 }
@@ -0,0 +1,289 @@
+use std::rc::Rc;
+
+use crate::decoder::{
+    self, CustomSection, Decoder, ExternalKind, FuncType, GlobalType, ImportType, LineTable,
+    NameSection, Sections,
+};
+use crate::error::YawError;
+use crate::types::{MemoryType, TableType};
+use crate::validator;
+
+use super::ir::Code;
+use super::{ImportResolver, InternalFunction, VM};
+
+/// The type of an imported or exported entity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExternType {
+    Function(FuncType),
+    Table(TableType),
+    Memory(MemoryType),
+    Global(GlobalType),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDescriptor<'m> {
+    pub module_name: &'m str,
+    pub field_name: &'m str,
+    pub ty: ExternType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDescriptor<'m> {
+    pub name: &'m str,
+    pub ty: ExternType,
+}
+
+/// A decoded and validated module whose function bodies are compiled once.
+/// Cloning is cheap, and every `VM` instantiated from it shares the compiled
+/// code while getting its own memories, tables and globals.
+#[derive(Debug, Clone)]
+pub struct Module(Rc<ModuleInner>);
+
+#[derive(Debug)]
+struct ModuleInner {
+    sections: Sections,
+    functions: Vec<InternalFunction>,
+    names: NameSection,
+    line_table: LineTable,
+}
+
+impl Module {
+    pub fn new<B: AsRef<[u8]>>(buf: B) -> Result<Module, YawError> {
+        let mut reader = buf.as_ref();
+        decoder::decode_preamble(&mut reader)?;
+        let sections = decoder::decode(reader)?;
+        validator::validate(&sections)?;
+        Ok(Module::from_section(sections))
+    }
+
+    /// Compiles the function bodies of already validated sections.
+    pub(crate) fn from_section(mut sections: Sections) -> Module {
+        let mut functions = vec![];
+        let imported = sections.import_section.as_ref().map_or(0, |s| {
+            s.entries
+                .iter()
+                .filter(|e| matches!(e.import_type, ImportType::Function(_)))
+                .count()
+        });
+        // The decoded bodies are not needed once compiled.
+        if let Some(code) = sections.code_section.take() {
+            let types = sections
+                .function_section
+                .as_ref()
+                .map_or(&[][..], |f| &f.types[..]);
+            // Empty function and code sections are valid without a type section.
+            let entries = sections
+                .type_section
+                .as_ref()
+                .map_or(&[][..], |t| &t.entries[..]);
+            for (i, b) in code.bodies.into_iter().enumerate() {
+                // Find type index from func section types
+                // Then Find arg signature from type section entries.
+                let func_type = &entries[types[i] as usize];
+                functions.push(InternalFunction {
+                    code: Rc::new(Code::compile(
+                        (imported + i) as u32,
+                        &b.decoded,
+                        b.offsets,
+                        entries,
+                    )),
+                    locals: b.locals,
+                    args: func_type.args.clone(),
+                    results: func_type.results.clone(),
+                });
+            }
+        }
+        // A malformed name section is ignored like any other custom section.
+        let names = sections
+            .custom_sections
+            .iter()
+            .find(|s| s.name == "name")
+            .and_then(|s| NameSection::decode(&mut &s.payload[..]).ok())
+            .unwrap_or_default();
+        let line_table = {
+            let custom = |name| {
+                sections
+                    .custom_sections
+                    .iter()
+                    .find(|s| s.name == name)
+                    .map_or(&[][..], |s| &s.payload[..])
+            };
+            LineTable::decode(
+                custom(".debug_line"),
+                custom(".debug_line_str"),
+                custom(".debug_str"),
+            )
+            .unwrap_or_default()
+        };
+        Module(Rc::new(ModuleInner {
+            sections,
+            functions,
+            names,
+            line_table,
+        }))
+    }
+
+    /// Creates an instance and runs its start function.
+    pub fn instantiate<'a>(
+        &self,
+        imports: Option<&'a dyn ImportResolver>,
+    ) -> Result<VM<'a>, YawError> {
+        let vm = self.instantiate_without_start(imports)?;
+        vm.start()?;
+        Ok(vm)
+    }
+
+    /// Same as `instantiate`, but leaves running the start function to the embedder
+    /// through `VM::start`.
+    pub fn instantiate_without_start<'a>(
+        &self,
+        imports: Option<&'a dyn ImportResolver>,
+    ) -> Result<VM<'a>, YawError> {
+        Ok(VM::instantiate(self, imports)?)
+    }
+
+    /// What the module imports, in the order of its import section.
+    pub fn imports(&self) -> Vec<ImportDescriptor<'_>> {
+        let sections = &self.0.sections;
+        let entries = sections
+            .import_section
+            .as_ref()
+            .map_or(&[][..], |i| &i.entries[..]);
+        entries
+            .iter()
+            .map(|entry| ImportDescriptor {
+                module_name: &entry.module_name,
+                field_name: &entry.field_name,
+                ty: match &entry.import_type {
+                    ImportType::Function(index) => {
+                        ExternType::Function(self.func_types()[*index as usize].clone())
+                    }
+                    ImportType::Table(t) => ExternType::Table(table_type(t)),
+                    ImportType::Memory(m) => ExternType::Memory(memory_type(m)),
+                    ImportType::Global(g) => ExternType::Global(g.clone()),
+                },
+            })
+            .collect()
+    }
+
+    /// What the module exports, in the order of its export section.
+    pub fn exports(&self) -> Vec<ExportDescriptor<'_>> {
+        let sections = &self.0.sections;
+        let imports = self.imports();
+        // Imported entities come first in each index space.
+        let imported = |kind| {
+            imports.iter().filter_map(move |i| match (&i.ty, kind) {
+                (ExternType::Function(_), ExternalKind::Function)
+                | (ExternType::Table(_), ExternalKind::Table)
+                | (ExternType::Memory(_), ExternalKind::Memory)
+                | (ExternType::Global(_), ExternalKind::Global) => Some(i.ty.clone()),
+                _ => None,
+            })
+        };
+        let functions: Vec<ExternType> = imported(ExternalKind::Function)
+            .chain(self.functions().iter().map(|f| {
+                ExternType::Function(FuncType {
+                    args: f.args.clone(),
+                    results: f.results.clone(),
+                })
+            }))
+            .collect();
+        let tables: Vec<ExternType> = imported(ExternalKind::Table)
+            .chain(
+                sections
+                    .table_section
+                    .iter()
+                    .flat_map(|t| t.entries.iter().map(|t| ExternType::Table(table_type(t)))),
+            )
+            .collect();
+        let memories: Vec<ExternType> = imported(ExternalKind::Memory)
+            .chain(
+                sections
+                    .memory_section
+                    .iter()
+                    .flat_map(|m| m.entries.iter().map(|m| ExternType::Memory(memory_type(m)))),
+            )
+            .collect();
+        let globals: Vec<ExternType> = imported(ExternalKind::Global)
+            .chain(sections.global_section.iter().flat_map(|g| {
+                g.globals
+                    .iter()
+                    .map(|g| ExternType::Global(g.global_type.clone()))
+            }))
+            .collect();
+
+        let entries = sections
+            .export_section
+            .as_ref()
+            .map_or(&[][..], |e| &e.entries[..]);
+        entries
+            .iter()
+            .map(|entry| {
+                let space = match entry.kind {
+                    ExternalKind::Function => &functions,
+                    ExternalKind::Table => &tables,
+                    ExternalKind::Memory => &memories,
+                    ExternalKind::Global => &globals,
+                };
+                ExportDescriptor {
+                    name: &entry.name,
+                    // Validated, so the index is in range.
+                    ty: space[entry.index as usize].clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Custom sections of the module, in the order they appear.
+    pub fn custom_sections(&self) -> &[CustomSection] {
+        &self.0.sections.custom_sections
+    }
+
+    /// Payload of the first custom section called `name`.
+    pub fn custom_section(&self, name: &str) -> Option<&[u8]> {
+        self.custom_sections()
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| &s.payload[..])
+    }
+
+    /// Names from the `name` section, empty if the module has none.
+    pub fn names(&self) -> &NameSection {
+        &self.0.names
+    }
+
+    /// Source lines from the DWARF `.debug_line` section, empty if the module has none.
+    pub fn line_table(&self) -> &LineTable {
+        &self.0.line_table
+    }
+
+    pub(crate) fn sections(&self) -> &Sections {
+        &self.0.sections
+    }
+
+    /// Functions defined by the module, following the imported ones in the index space.
+    pub(crate) fn functions(&self) -> &[InternalFunction] {
+        &self.0.functions
+    }
+
+    pub(crate) fn func_types(&self) -> &[FuncType] {
+        self.0
+            .sections
+            .type_section
+            .as_ref()
+            .map_or(&[], |t| &t.entries[..])
+    }
+}
+
+fn table_type(t: &decoder::TableType) -> TableType {
+    TableType {
+        element_type: t.elem_type,
+        limits: t.limits.clone(),
+    }
+}
+
+fn memory_type(m: &decoder::MemoryType) -> MemoryType {
+    MemoryType {
+        limits: m.limits.clone(),
+    }
+}
@@ -1,30 +1,106 @@
+use crate::decoder::SegmentMode;
 use crate::types::*;
 
-use super::FunctionInstanceRef;
-use super::{Functions, Globals, ImportResolver, ImportType, RuntimeError, Sections};
+use super::{Functions, Globals, ImportResolver, ImportType, RuntimeError, Sections, TrapCode};
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// Upper bound of the size of a table without a maximum, to keep `table.grow`
+/// from exhausting the host memory.
+const MAX_TABLE_SIZE: u32 = 10_000_000;
+
 #[derive(Debug, Clone)]
 pub struct TableInstance {
-    pub entries: Vec<Option<FunctionInstanceRef>>,
+    /// References of `elem_type`.
+    pub entries: Vec<RuntimeValue>,
+    pub elem_type: ElemType,
     pub limits: ResizableLimits,
 }
 
 impl TableInstance {
+    /// Creates a table of `funcref`s.
     pub fn new(initial: u32, max: Option<u32>) -> Self {
+        Self::with_elem_type(ElemType::AnyFunc, initial, max)
+    }
+
+    pub fn with_elem_type(elem_type: ElemType, initial: u32, max: Option<u32>) -> Self {
         Self {
-            entries: vec![None; initial as usize],
+            entries: vec![ValueType::from(elem_type).into(); initial as usize],
+            elem_type,
             limits: ResizableLimits::new(initial, max),
         }
     }
 
-    pub fn get(&self, index: usize) -> FunctionInstanceRef {
-        self.entries[index].as_ref().unwrap().clone()
+    pub fn get(&self, index: usize) -> Result<RuntimeValue, RuntimeError> {
+        let v = self
+            .entries
+            .get(index)
+            .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+        Ok(v.clone())
+    }
+
+    pub fn set(&mut self, index: usize, value: RuntimeValue) -> Result<(), RuntimeError> {
+        let entry = self
+            .entries
+            .get_mut(index)
+            .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+        *entry = value;
+        Ok(())
+    }
+
+    pub fn size(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    /// Appends `delta` copies of `init`, returning the previous size or -1 if the
+    /// table can not grow that much.
+    pub fn grow(&mut self, delta: u32, init: RuntimeValue) -> i32 {
+        let size = self.size();
+        let max = self.limits.maximum.unwrap_or(MAX_TABLE_SIZE);
+        match size.checked_add(delta) {
+            Some(len) if len <= max => {
+                self.entries.resize(len as usize, init);
+                size as i32
+            }
+            _ => -1,
+        }
+    }
+
+    /// Sets `len` entries from `offset` to `value`, or traps without writing
+    /// anything if they are not all in bounds.
+    pub fn fill(
+        &mut self,
+        offset: usize,
+        value: RuntimeValue,
+        len: usize,
+    ) -> Result<(), RuntimeError> {
+        let dst = self
+            .entries
+            .get_mut(offset..offset + len)
+            .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+        dst.iter_mut().for_each(|e| *e = value.clone());
+        Ok(())
     }
 
-    pub fn set(&mut self, index: usize, value: Option<FunctionInstanceRef>) {
-        self.entries[index] = value;
+    /// Writes `elems` from `offset`, or traps without writing anything if
+    /// they do not fit.
+    pub fn init(&mut self, offset: usize, elems: &[RuntimeValue]) -> Result<(), RuntimeError> {
+        let dst = self
+            .entries
+            .get_mut(offset..offset + elems.len())
+            .ok_or(TrapCode::OutOfBoundsTableAccess)?;
+        dst.clone_from_slice(elems);
+        Ok(())
+    }
+
+    /// Copies `len` entries from `src` to `dst`, where the ranges may overlap.
+    pub fn copy_within(&mut self, src: usize, dst: usize, len: usize) -> Result<(), RuntimeError> {
+        if src + len > self.entries.len() || dst + len > self.entries.len() {
+            return Err(TrapCode::OutOfBoundsTableAccess.into());
+        }
+        let elems = self.entries[src..src + len].to_vec();
+        self.entries[dst..dst + len].clone_from_slice(&elems);
+        Ok(())
     }
 }
 
@@ -41,59 +117,54 @@ impl Tables {
         imports: Option<&'a dyn ImportResolver>,
     ) -> Result<Self, RuntimeError> {
         let mut tables = vec![];
-
-        let mut table = Rc::new(RefCell::new(TableInstance::new(0, None)));
-        let mut table_entries: Vec<Option<u32>> = vec![];
         if let Some(import_section) = sections.import_section.as_ref() {
             if let Some(imports) = &imports {
                 for entry in &import_section.entries {
                     if let ImportType::Table(_) = entry.import_type {
-                        table = imports
-                            .resolve_table(entry.module_name.clone(), entry.field_name.clone())?;
-                        table_entries = vec![None; table.borrow().limits.initial as usize];
-                        if let Some(elem_section) = sections.element_section.as_ref() {
-                            for i in 0..elem_section.entries.len() {
-                                let offset: usize =
-                                    elem_section.entries[i].offset.eval(&globals)?.into();
-
-                                for index in 0..elem_section.entries[i].elems.len() {
-                                    table_entries[offset + index as usize] =
-                                        Some(elem_section.entries[i].elems[index]);
-                                }
-                            }
-                        }
+                        tables.push(
+                            imports.resolve_table(
+                                entry.module_name.clone(),
+                                entry.field_name.clone(),
+                            )?,
+                        );
                     }
                 }
             }
         }
 
         if let Some(t) = sections.table_section.as_ref() {
-            let t = &t.entries[0];
-            let len = t.limits.initial;
-            let max = t.limits.maximum;
-            if let Some(elem_section) = sections.element_section.as_ref() {
-                table = Rc::new(RefCell::new(TableInstance::new(len, max)));
-                table_entries = vec![None; len as usize];
-                for i in 0..elem_section.entries.len() {
-                    let offset: usize = elem_section.entries[i].offset.eval(&globals)?.into();
-                    for index in 0..elem_section.entries[i].elems.len() {
-                        table_entries[offset + index as usize] =
-                            Some(elem_section.entries[i].elems[index]);
-                    }
-                }
+            for t in &t.entries {
+                let table =
+                    TableInstance::with_elem_type(t.elem_type, t.limits.initial, t.limits.maximum);
+                tables.push(Rc::new(RefCell::new(table)));
             }
-        };
+        }
 
-        for (i, item) in table_entries.iter().enumerate() {
-            if let Some(fn_index) = item {
-                table.borrow_mut().entries[i] = Some(functions.get_ref(*fn_index as usize)?);
-            }
+        if tables.is_empty() {
+            tables.push(Rc::new(RefCell::new(TableInstance::new(0, None))));
         }
 
-        tables.push(table);
+        // Active segments are copied in order, so the ones before a segment
+        // out of bounds stay written. Passive segments wait for `table.init`.
+        if let Some(elem_section) = sections.element_section.as_ref() {
+            for segment in &elem_section.entries {
+                if let SegmentMode::Active { index, offset } = &segment.mode {
+                    let table = tables
+                        .get(*index as usize)
+                        .ok_or(RuntimeError::UndefinedTableError)?;
+                    let offset = offset.eval_offset(globals, functions)? as usize;
+                    let elems = segment.eval_elems(globals, functions)?;
+                    table.borrow_mut().init(offset, &elems)?;
+                }
+            }
+        }
         Ok(Self(tables))
     }
 
+    pub fn get(&self, index: usize) -> Option<&TableRef> {
+        self.0.get(index)
+    }
+
     pub fn get_ref(&self, index: usize) -> Result<TableRef, RuntimeError> {
         let t = self.0.get(index).ok_or(RuntimeError::UndefinedTableError)?;
         Ok(Rc::clone(t))
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use failure::Fail;
+
+use crate::decoder::SourceLocation;
+
+/// The reason why the execution of a guest was aborted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrapCode {
+    Unreachable,
+    OutOfBoundsMemoryAccess,
+    OutOfBoundsTableAccess,
+    DivisionByZero,
+    IntegerOverflow,
+    UnrepresentableInteger,
+    IndirectCallTypeMismatch,
+    UndefinedElement,
+    UninitializedElement,
+    StackOverflow,
+    OutOfFuel,
+    Interrupted,
+}
+
+impl fmt::Display for TrapCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            TrapCode::Unreachable => "unreachable",
+            TrapCode::OutOfBoundsMemoryAccess => "out of bounds memory access",
+            TrapCode::OutOfBoundsTableAccess => "out of bounds table access",
+            TrapCode::DivisionByZero => "integer divide by zero",
+            TrapCode::IntegerOverflow => "integer overflow",
+            TrapCode::UnrepresentableInteger => "invalid conversion to integer",
+            TrapCode::IndirectCallTypeMismatch => "indirect call type mismatch",
+            TrapCode::UndefinedElement => "undefined element",
+            TrapCode::UninitializedElement => "uninitialized element",
+            TrapCode::StackOverflow => "call stack exhausted",
+            TrapCode::OutOfFuel => "all fuel consumed",
+            TrapCode::Interrupted => "interrupted",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// A guest function activation at the time of a trap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameInfo {
+    pub func_index: u32,
+    /// Name of the function from the `name` section.
+    pub func_name: Option<String>,
+    /// Index of the instruction in the function body.
+    pub pc: usize,
+    /// Offset of the instruction from the start of the code section.
+    pub code_offset: Option<u32>,
+    /// Source line of the instruction, if the module has DWARF line information.
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for FrameInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.func_name {
+            Some(name) => write!(f, "{}", name)?,
+            None => write!(f, "#{}", self.func_index)?,
+        }
+        match (&self.location, self.code_offset) {
+            (Some(location), _) => write!(f, " at {}", location),
+            (None, Some(offset)) => write!(f, " at pc {} (code offset {:#x})", self.pc, offset),
+            (None, None) => write!(f, " at pc {}", self.pc),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Trap {
+    code: TrapCode,
+    /// Guest frames from the innermost one, empty if the trap was not raised by a guest.
+    backtrace: Vec<FrameInfo>,
+}
+
+impl Trap {
+    pub fn new(code: TrapCode) -> Self {
+        Trap {
+            code,
+            backtrace: vec![],
+        }
+    }
+
+    pub fn code(&self) -> TrapCode {
+        self.code
+    }
+
+    pub fn backtrace(&self) -> &[FrameInfo] {
+        &self.backtrace
+    }
+
+    /// Index of the function that trapped.
+    pub fn function(&self) -> Option<u32> {
+        self.backtrace.first().map(|f| f.func_index)
+    }
+
+    /// Name of the function that trapped, from the `name` section.
+    pub fn function_name(&self) -> Option<&str> {
+        self.backtrace.first().and_then(|f| f.func_name.as_deref())
+    }
+
+    pub(crate) fn with_backtrace(mut self, backtrace: Vec<FrameInfo>) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+}
+
+/// Shows the trap and the function it happened in, with the source line if known.
+/// The alternate form `{:#}` lists the whole backtrace as well.
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code)?;
+        if let Some(frame) = self.backtrace.first() {
+            match &frame.func_name {
+                Some(name) => write!(f, " in function {}", name)?,
+                None => write!(f, " in function #{}", frame.func_index)?,
+            }
+            if let Some(location) = &frame.location {
+                write!(f, " at {}", location)?;
+            }
+        }
+        if f.alternate() {
+            for (i, frame) in self.backtrace.iter().enumerate() {
+                write!(f, "\n  {}: {}", i, frame)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Fail for Trap {}
+
+impl From<TrapCode> for Trap {
+    fn from(code: TrapCode) -> Self {
+        Trap::new(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn display_spec_message() {
+        let trap = Trap::new(TrapCode::DivisionByZero);
+        assert_eq!(trap.code(), TrapCode::DivisionByZero);
+        assert_eq!(trap.to_string(), "integer divide by zero");
+    }
+
+    #[test]
+    fn display_backtrace() {
+        let frame = |func_index, func_name: Option<&str>, code_offset| FrameInfo {
+            func_index,
+            func_name: func_name.map(|s| s.to_owned()),
+            pc: 2,
+            code_offset,
+            location: None,
+        };
+        let trap = Trap::new(TrapCode::Unreachable).with_backtrace(vec![frame(3, None, None)]);
+        assert_eq!(trap.to_string(), "unreachable in function #3");
+        let trap = Trap::new(TrapCode::Unreachable).with_backtrace(vec![
+            frame(3, Some("fac"), Some(0x2a)),
+            frame(4, None, Some(0x30)),
+        ]);
+        assert_eq!(trap.function(), Some(3));
+        assert_eq!(trap.to_string(), "unreachable in function fac");
+        assert_eq!(
+            format!("{:#}", trap),
+            "unreachable in function fac\n  0: fac at pc 2 (code offset 0x2a)\n  1: #4 at pc 2 (code offset 0x30)"
+        );
+    }
+
+    #[test]
+    fn display_source_location() {
+        let trap = Trap::new(TrapCode::Unreachable).with_backtrace(vec![FrameInfo {
+            func_index: 3,
+            func_name: Some("fac".to_owned()),
+            pc: 2,
+            code_offset: Some(0x2a),
+            location: Some(SourceLocation {
+                file: "src/lib.rs".to_owned(),
+                line: 42,
+                column: 5,
+            }),
+        }]);
+        assert_eq!(
+            trap.to_string(),
+            "unreachable in function fac at src/lib.rs:42:5"
+        );
+        assert_eq!(
+            format!("{:#}", trap),
+            "unreachable in function fac at src/lib.rs:42:5\n  0: fac at src/lib.rs:42:5"
+        );
+    }
+}
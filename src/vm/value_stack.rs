@@ -23,6 +23,26 @@ impl ValueStack {
         ::std::mem::replace(&mut self.buf, vec![])
     }
 
+    pub fn get(&self, index: usize) -> Option<RuntimeValue> {
+        self.buf.get(index).cloned()
+    }
+
+    pub fn set(&mut self, index: usize, v: RuntimeValue) -> Option<()> {
+        let slot = self.buf.get_mut(index)?;
+        *slot = v;
+        Some(())
+    }
+
+    /// Moves the top `keep` values down to `len` and drops everything above them.
+    pub fn drop_keep(&mut self, len: usize, keep: usize) -> Option<()> {
+        let top = self.buf.len().checked_sub(keep)?;
+        if top < len {
+            return None;
+        }
+        self.buf.drain(len..top);
+        Some(())
+    }
+
     pub fn len(&self) -> usize {
         self.buf.len()
     }
@@ -31,3 +51,27 @@ impl ValueStack {
         self.buf.len() == 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn drop_keep_moves_results_down() {
+        let mut stack = ValueStack::new();
+        for v in 0..5 {
+            stack.push(RuntimeValue::I32(v));
+        }
+        assert_eq!(stack.drop_keep(1, 2), Some(()));
+        assert_eq!(
+            stack.take_buf(),
+            vec![
+                RuntimeValue::I32(0),
+                RuntimeValue::I32(3),
+                RuntimeValue::I32(4)
+            ]
+        );
+        assert_eq!(stack.drop_keep(0, 1), None);
+    }
+}
@@ -3,14 +3,16 @@ extern crate yaw;
 mod spectest;
 
 use spectest::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::convert::TryInto;
 use std::fs;
 use std::io::Read;
 use std::rc::Rc;
 
 use yaw::types::*;
 use yaw::{
-    FunctionResolver, Global, Imports, MemoryDescriptor, MemoryRef, RuntimeError, ValueType,
+    ExternType, FuncType, FunctionResolver, Global, GlobalType, Imports, InvocationResult,
+    MemoryDescriptor, MemoryRef, Module, RuntimeError, Suspension, TrapCode, ValueType,
 };
 
 #[test]
@@ -96,7 +98,7 @@ fn if_wasm() -> Result<(), yaw::error::YawError> {
     let mut buf = vec![];
     file.read_to_end(&mut buf)?;
     let ins = yaw::instantiate(&buf, None)?;
-    let ret = ins.invoke("if", &[])?;
+    let ret = ins.invoke("if", &[RuntimeValue::I32(1)])?;
     assert_eq!(vec![RuntimeValue::I32(10)], ret);
     Ok(())
 }
@@ -130,15 +132,15 @@ fn i32_load() -> Result<(), yaw::error::YawError> {
     let mut file = fs::File::open("./fixtures/wasm/load.wasm")?;
     let mut buf = vec![];
     file.read_to_end(&mut buf)?;
-    let mem = MemoryRef::new(MemoryDescriptor::new(1, None));
+    let mem = MemoryRef::new(MemoryDescriptor::new(1, None))?;
     mem.i32_store(0, 0x1234_5678)?;
     let mut imports = Imports::new();
     imports.add_memory("env", "memory", mem.clone());
     let ins = yaw::instantiate(&buf, Some(&imports))?;
-    let ret = ins.invoke("load", &[])?;
+    let ret = ins.invoke("load", &[RuntimeValue::I32(0), RuntimeValue::I32(0)])?;
     assert_eq!(vec![RuntimeValue::I32(0x1234_5678)], ret);
     mem.i32_store(0, 0x5A5A_A5A5)?;
-    let ret = ins.invoke("load", &[])?;
+    let ret = ins.invoke("load", &[RuntimeValue::I32(0), RuntimeValue::I32(0)])?;
     assert_eq!(vec![RuntimeValue::I32(0x5A5A_A5A5)], ret);
     Ok(())
 }
@@ -148,11 +150,11 @@ fn i32_store() -> Result<(), yaw::error::YawError> {
     let mut file = fs::File::open("./fixtures/wasm/store.wasm")?;
     let mut buf = vec![];
     file.read_to_end(&mut buf)?;
-    let mem = MemoryRef::new(MemoryDescriptor::new(1, None));
+    let mem = MemoryRef::new(MemoryDescriptor::new(1, None))?;
     let mut imports = Imports::new();
     imports.add_memory("env", "memory", mem);
     let ins = yaw::instantiate(&buf, Some(&imports))?;
-    let ret = ins.invoke("store", &[])?;
+    let ret = ins.invoke("store", &[RuntimeValue::I32(0), RuntimeValue::I32(0)])?;
     assert_eq!(vec![RuntimeValue::I32(0x5A5A_A5A5)], ret);
     Ok(())
 }
@@ -179,13 +181,426 @@ fn global() -> Result<(), yaw::error::YawError> {
     Ok(())
 }
 
+#[test]
+fn start() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/start.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    let ret = ins.invoke("get", &[])?;
+    assert_eq!(vec![RuntimeValue::I32(42)], ret);
+    Ok(())
+}
+
+#[test]
+fn start_manually() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/start.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate_without_start(&buf, None)?;
+    assert_eq!(vec![RuntimeValue::I32(0)], ins.invoke("get", &[])?);
+    ins.start()?;
+    assert_eq!(vec![RuntimeValue::I32(42)], ins.invoke("get", &[])?);
+    Ok(())
+}
+
+#[test]
+fn instantiate_module_many_times() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/start.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let module = yaw::Module::new(&buf)?;
+    let started = module.instantiate(None)?;
+    let fresh = module.instantiate_without_start(None)?;
+    assert_eq!(vec![RuntimeValue::I32(42)], started.invoke("get", &[])?);
+    assert_eq!(vec![RuntimeValue::I32(0)], fresh.invoke("get", &[])?);
+    Ok(())
+}
+
+#[test]
+fn describe_imports_and_exports() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/global_import.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let module = yaw::Module::new(&buf)?;
+    let imports = module.imports();
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].module_name, "env");
+    assert_eq!(imports[0].field_name, "global1");
+    assert_eq!(
+        imports[0].ty,
+        ExternType::Global(GlobalType {
+            mutability: false,
+            value_type: ValueType::I32,
+        })
+    );
+    let exports = module.exports();
+    assert_eq!(exports.len(), 1);
+    assert_eq!(exports[0].name, "global");
+    assert_eq!(
+        exports[0].ty,
+        ExternType::Function(FuncType {
+            args: vec![ValueType::I32, ValueType::I32],
+            results: vec![ValueType::I32],
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn read_custom_sections() -> Result<(), yaw::error::YawError> {
+    let buf = vec![
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x00, 0x06, 0x04, 0x6e, 0x6f, 0x74, 0x65, 0x2a, // custom "note" [42]
+        0x00, 0x06, 0x04, 0x6e, 0x6f, 0x74, 0x65, 0x2b, // custom "note" [43]
+    ];
+    let module = yaw::Module::new(&buf)?;
+    assert_eq!(module.custom_sections().len(), 2);
+    assert_eq!(module.custom_section("note"), Some(&[42][..]));
+    assert_eq!(module.custom_section("name"), None);
+    Ok(())
+}
+
+#[test]
+fn bulk_memory() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/bulk_memory.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    let load8 = |addr: i32| ins.invoke("load8", &[RuntimeValue::I32(addr)]);
+    // The passive segment is not written at instantiation.
+    assert_eq!(load8(0)?, vec![RuntimeValue::I32(0)]);
+    ins.invoke("init", &[RuntimeValue::I32(10)])?;
+    assert_eq!(load8(10)?, vec![RuntimeValue::I32(b'h' as i32)]);
+    assert_eq!(load8(14)?, vec![RuntimeValue::I32(b'o' as i32)]);
+    let args = [12, 10, 5].map(RuntimeValue::I32);
+    ins.invoke("copy", &args)?;
+    assert_eq!(load8(12)?, vec![RuntimeValue::I32(b'h' as i32)]);
+    assert_eq!(load8(16)?, vec![RuntimeValue::I32(b'o' as i32)]);
+    ins.invoke("fill", &[10, 0x2A, 2].map(RuntimeValue::I32))?;
+    assert_eq!(load8(11)?, vec![RuntimeValue::I32(0x2A)]);
+    assert_eq!(load8(12)?, vec![RuntimeValue::I32(b'h' as i32)]);
+    // Out of bounds fills trap without writing anything.
+    let ret = ins.invoke("fill", &[65535, 1, 2].map(RuntimeValue::I32));
+    assert!(matches!(ret, Err(yaw::YawError::Trap { trap })
+        if trap.code() == TrapCode::OutOfBoundsMemoryAccess));
+    assert_eq!(load8(65535)?, vec![RuntimeValue::I32(0)]);
+    // A dropped segment is empty.
+    ins.invoke("drop", &[])?;
+    let ret = ins.invoke("init", &[RuntimeValue::I32(0)]);
+    assert!(matches!(ret, Err(yaw::YawError::Trap { trap })
+        if trap.code() == TrapCode::OutOfBoundsMemoryAccess));
+    Ok(())
+}
+
+#[test]
+fn reference_types() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/reference_types.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    // `call_indirect` goes through the second table.
+    assert_eq!(
+        ins.invoke("call", &[RuntimeValue::I32(0)])?,
+        vec![RuntimeValue::I32(1)]
+    );
+    let ret = ins.invoke("call", &[RuntimeValue::I32(1)]);
+    assert!(matches!(ret, Err(yaw::YawError::Trap { trap })
+        if trap.code() == TrapCode::UninitializedElement));
+    ins.invoke("set_two", &[RuntimeValue::I32(1)])?;
+    assert_eq!(
+        ins.invoke("call", &[RuntimeValue::I32(1)])?,
+        vec![RuntimeValue::I32(2)]
+    );
+    // External references round trip through a table.
+    let hello = ExternRef::new(String::from("hello"));
+    let ret = ins.invoke("push", &[RuntimeValue::ExternRef(Some(hello.clone()))])?;
+    assert_eq!(ret, vec![RuntimeValue::I32(0)]);
+    ins.invoke("push", &[RuntimeValue::ExternRef(None)])?;
+    assert_eq!(ins.invoke("size", &[])?, vec![RuntimeValue::I32(2)]);
+    let ret = ins.invoke("get", &[RuntimeValue::I32(0)])?;
+    assert_eq!(ret, vec![RuntimeValue::ExternRef(Some(hello.clone()))]);
+    match &ret[0] {
+        RuntimeValue::ExternRef(Some(r)) => {
+            assert_eq!(
+                r.downcast_ref::<String>().map(|s| s.as_str()),
+                Some("hello")
+            )
+        }
+        v => panic!("expected an externref, got {:?}", v),
+    }
+    let ret = ins.invoke("get", &[RuntimeValue::I32(1)])?;
+    assert_eq!(ret, vec![RuntimeValue::ExternRef(None)]);
+    let ret = ins.invoke("is_null", &[RuntimeValue::ExternRef(Some(hello))])?;
+    assert_eq!(ret, vec![RuntimeValue::I32(0)]);
+    let ret = ins.invoke("get", &[RuntimeValue::I32(2)]);
+    assert!(matches!(ret, Err(yaw::YawError::Trap { trap })
+        if trap.code() == TrapCode::OutOfBoundsTableAccess));
+    Ok(())
+}
+
+#[test]
+fn multi_value() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/multi_value.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    let ret = ins.invoke("swap", &[RuntimeValue::I32(1), RuntimeValue::I32(2)])?;
+    assert_eq!(vec![RuntimeValue::I32(2), RuntimeValue::I32(1)], ret);
+    let ret = ins.invoke("div_rem", &[RuntimeValue::I32(17), RuntimeValue::I32(5)])?;
+    assert_eq!(vec![RuntimeValue::I32(3), RuntimeValue::I32(2)], ret);
+    let ret = ins.invoke("sum", &[RuntimeValue::I32(10)])?;
+    assert_eq!(vec![RuntimeValue::I32(10), RuntimeValue::I32(55)], ret);
+    Ok(())
+}
+
+#[test]
+fn name_trapped_function() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/trap.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    match ins.invoke("divide_by_zero", &[]) {
+        Err(yaw::YawError::Trap { trap }) => {
+            assert_eq!(trap.code(), TrapCode::DivisionByZero);
+            assert_eq!(trap.function(), Some(0));
+            assert_eq!(trap.function_name(), Some("divide"));
+            assert_eq!(
+                trap.to_string(),
+                "integer divide by zero in function divide"
+            );
+        }
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    let index = ins.module().names().function_index("divide").unwrap();
+    let ret = ins.invoke_function(index, &[RuntimeValue::I32(6), RuntimeValue::I32(3)])?;
+    assert_eq!(vec![RuntimeValue::I32(2)], ret);
+    Ok(())
+}
+
+#[test]
+fn trap_backtrace() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/trap.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    match ins.invoke("divide_by_zero", &[]) {
+        Err(yaw::YawError::Trap { trap }) => {
+            let frames = trap.backtrace();
+            assert_eq!(frames.len(), 2);
+            assert_eq!(frames[0].func_index, 0);
+            assert_eq!(frames[0].func_name.as_deref(), Some("divide"));
+            assert_eq!(frames[0].pc, 2);
+            assert_eq!(frames[1].func_index, 1);
+            assert_eq!(frames[1].func_name, None);
+            assert_eq!(frames[1].pc, 2);
+            assert!(frames[0].code_offset < frames[1].code_offset);
+            assert!(format!("{:#}", trap).ends_with(&format!("\n  1: {}", frames[1])));
+        }
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    Ok(())
+}
+
+#[test]
+fn out_of_bounds_backtrace() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/trap.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    match ins.invoke("out_of_bounds", &[]) {
+        Err(yaw::YawError::Trap { trap }) => {
+            assert_eq!(trap.code(), TrapCode::OutOfBoundsMemoryAccess);
+            let frames = trap.backtrace();
+            assert_eq!(frames.len(), 2);
+            assert_eq!(frames[0].func_index, 2);
+            assert_eq!(frames[1].func_index, 3);
+        }
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    // A second fault is caught just like the first one.
+    let ret = ins.invoke("out_of_bounds", &[]);
+    assert!(matches!(ret, Err(yaw::YawError::Trap { trap })
+        if trap.code() == TrapCode::OutOfBoundsMemoryAccess));
+    Ok(())
+}
+
+/// A DWARF 4 `.debug_line` custom section that puts each `(code offset, line, column)`
+/// in `src/lib.rs`.
+fn debug_line_section(rows: &[(u32, u8, u8)]) -> Vec<u8> {
+    let mut header = vec![0x01, 0x01, 0x01, 0xfb, 0x0e, 0x0d];
+    header.extend(&[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+    header.push(0x00); // no include directories
+    header.extend(b"src/lib.rs\0\0\0\0");
+    header.push(0x00);
+    let mut unit = vec![0x04, 0x00];
+    unit.extend(&(header.len() as u32).to_le_bytes());
+    unit.extend(header);
+    for &(offset, line, column) in rows {
+        unit.extend(&[0x00, 0x05, 0x02]);
+        unit.extend(&offset.to_le_bytes());
+        unit.extend(&[0x03, line - 1, 0x05, column, 0x01]);
+        unit.extend(&[0x02, 0x01, 0x00, 0x01, 0x01]);
+    }
+    let mut payload = vec![11];
+    payload.extend(b".debug_line");
+    payload.extend(&(unit.len() as u32).to_le_bytes());
+    payload.extend(unit);
+    let mut section = vec![0x00, payload.len() as u8];
+    section.extend(payload);
+    section
+}
+
+#[test]
+fn trap_source_location() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/trap.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let offsets: Vec<u32> = match yaw::instantiate(&buf, None)?.invoke("divide_by_zero", &[]) {
+        Err(yaw::YawError::Trap { trap }) => trap
+            .backtrace()
+            .iter()
+            .map(|f| f.code_offset.unwrap())
+            .collect(),
+        ret => panic!("unexpected result {:?}", ret),
+    };
+    buf.extend(debug_line_section(&[
+        (offsets[0], 3, 5),
+        (offsets[1], 9, 0),
+    ]));
+    let module = Module::new(&buf)?;
+    assert!(module.line_table().find(offsets[0]).is_some());
+    match module.instantiate(None)?.invoke("divide_by_zero", &[]) {
+        Err(yaw::YawError::Trap { trap }) => {
+            assert_eq!(
+                trap.to_string(),
+                "integer divide by zero in function divide at src/lib.rs:3:5"
+            );
+            let location = trap.backtrace()[1].location.as_ref().unwrap();
+            assert_eq!((&location.file[..], location.line), ("src/lib.rs", 9));
+        }
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    Ok(())
+}
+
+#[test]
+fn call_stack_exhaustion() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/recursion.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let mut ins = yaw::instantiate(&buf, None)?;
+    ins.set_max_call_depth(100);
+    match ins.invoke("runaway", &[]) {
+        Err(yaw::YawError::Trap { trap }) => assert_eq!(trap.code(), TrapCode::StackOverflow),
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    Ok(())
+}
+
+#[test]
+fn value_stack_exhaustion_by_locals() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/recursion.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let mut ins = yaw::instantiate(&buf, None)?;
+    ins.set_max_value_stack_size(3);
+    match ins.invoke("locals", &[]) {
+        Err(yaw::YawError::Trap { trap }) => assert_eq!(trap.code(), TrapCode::StackOverflow),
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    ins.set_max_value_stack_size(4);
+    assert_eq!(ins.invoke("locals", &[])?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn fuel() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/recursion.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    assert_eq!(ins.remaining_fuel(), None);
+    ins.set_fuel(50);
+    match ins.invoke("runaway", &[]) {
+        Err(yaw::YawError::Trap { trap }) => assert_eq!(trap.code(), TrapCode::OutOfFuel),
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    assert_eq!(ins.remaining_fuel(), Some(0));
+    ins.add_fuel(10);
+    assert_eq!(ins.remaining_fuel(), Some(10));
+    Ok(())
+}
+
+#[test]
+fn resume_after_out_of_fuel() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/sum.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    ins.set_fuel(10);
+    let mut invocation = ins.invoke_resumable("sum", &[RuntimeValue::I32(10)])?;
+    let mut suspended = 0;
+    let ret = loop {
+        match invocation.resume()? {
+            InvocationResult::Finished(ret) => break ret,
+            InvocationResult::Suspended(reason) => {
+                assert_eq!(reason, Suspension::OutOfFuel);
+                suspended += 1;
+                ins.add_fuel(10);
+            }
+        }
+    };
+    assert!(suspended > 0);
+    assert_eq!(vec![RuntimeValue::I32(45)], ret);
+    Ok(())
+}
+
+#[test]
+fn interrupt() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/infinite_loop.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    let handle = ins.interrupt_handle();
+    let timer = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        handle.interrupt();
+    });
+    match ins.invoke("infinite_loop", &[]) {
+        Err(yaw::YawError::Trap { trap }) => assert_eq!(trap.code(), TrapCode::Interrupted),
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    timer.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn interrupt_before_invoke() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/sum.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let ins = yaw::instantiate(&buf, None)?;
+    ins.interrupt_handle().interrupt();
+    match ins.invoke("sum", &[RuntimeValue::I32(10)]) {
+        Err(yaw::YawError::Trap { trap }) => assert_eq!(trap.code(), TrapCode::Interrupted),
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    let ret = ins.invoke("sum", &[RuntimeValue::I32(10)])?;
+    assert_eq!(vec![RuntimeValue::I32(45)], ret);
+    Ok(())
+}
+
 #[test]
 fn i32_store_without_import() -> Result<(), yaw::error::YawError> {
     let mut file = fs::File::open("./fixtures/wasm/store_without_import.wasm")?;
     let mut buf = vec![];
     file.read_to_end(&mut buf)?;
     let ins = yaw::instantiate(&buf, None)?;
-    let ret = ins.invoke("store_without_import", &[])?;
+    let ret = ins.invoke(
+        "store_without_import",
+        &[RuntimeValue::I32(0), RuntimeValue::I32(0)],
+    )?;
     assert_eq!(vec![RuntimeValue::I32(0x5A5A_A5A5)], ret);
     Ok(())
 }
@@ -255,7 +670,7 @@ impl FunctionResolver for ImportFuncTest {
         _field_name: &str,
         args: &[RuntimeValue],
     ) -> Result<Vec<RuntimeValue>, RuntimeError> {
-        let v: usize = args[0].into();
+        let v: usize = args[0].clone().try_into()?;
         Ok(vec![RuntimeValue::I32((v * 2) as i32)])
     }
 }
@@ -269,11 +684,165 @@ fn import_func() -> Result<(), yaw::error::YawError> {
     let mut imports = Imports::new();
     imports.add_function(&r);
     let ins = yaw::instantiate(&buf, Some(&imports))?;
-    let ret = ins.invoke("exported_func", &[RuntimeValue::I32(0)])?;
+    let ret = ins.invoke("exported_func", &[])?;
     assert_eq!(vec![RuntimeValue::I32(84)], ret);
     Ok(())
 }
 
+#[derive(Debug, Clone)]
+struct YieldingImportFuncTest;
+
+impl FunctionResolver for YieldingImportFuncTest {
+    fn invoke(
+        &self,
+        _name: &str,
+        _field_name: &str,
+        args: &[RuntimeValue],
+    ) -> Result<Vec<RuntimeValue>, RuntimeError> {
+        let v: usize = args[0].clone().try_into()?;
+        Ok(vec![RuntimeValue::I32((v * 2) as i32)])
+    }
+
+    fn yield_requested(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn resume_after_host_yield() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/import_func.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let r = YieldingImportFuncTest {};
+    let mut imports = Imports::new();
+    imports.add_function(&r);
+    let ins = yaw::instantiate(&buf, Some(&imports))?;
+    let mut invocation = ins.invoke_resumable("exported_func", &[])?;
+    assert_eq!(
+        InvocationResult::Suspended(Suspension::Yield),
+        invocation.resume()?
+    );
+    assert_eq!(
+        InvocationResult::Finished(vec![RuntimeValue::I32(84)]),
+        invocation.resume()?
+    );
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct CountingImportFuncTest {
+    calls: Cell<u32>,
+}
+
+impl FunctionResolver for CountingImportFuncTest {
+    fn invoke(
+        &self,
+        _name: &str,
+        _field_name: &str,
+        args: &[RuntimeValue],
+    ) -> Result<Vec<RuntimeValue>, RuntimeError> {
+        self.calls.set(self.calls.get() + 1);
+        let v: usize = args[0].clone().try_into()?;
+        Ok(vec![RuntimeValue::I32((v * 2) as i32)])
+    }
+}
+
+#[test]
+fn resume_exported_host_function() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/import_func.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let r = CountingImportFuncTest::default();
+    let mut imports = Imports::new();
+    imports.add_function(&r);
+    let ins = yaw::instantiate(&buf, Some(&imports))?;
+    let mut invocation = ins.invoke_resumable("imported_func", &[RuntimeValue::I32(21)])?;
+    assert_eq!(r.calls.get(), 0);
+    assert_eq!(
+        InvocationResult::Finished(vec![RuntimeValue::I32(42)]),
+        invocation.resume()?
+    );
+    assert_eq!(r.calls.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn call_indirect_host_function() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/import_func.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let r = CountingImportFuncTest::default();
+    let mut imports = Imports::new();
+    imports.add_function(&r);
+    let ins = yaw::instantiate(&buf, Some(&imports))?;
+    let ret = ins.invoke("call_imported_indirect", &[RuntimeValue::I32(21)])?;
+    assert_eq!(vec![RuntimeValue::I32(42)], ret);
+    match ins.invoke("call_imported_mistyped", &[]) {
+        Err(yaw::YawError::Trap { trap }) => {
+            assert_eq!(trap.code(), TrapCode::IndirectCallTypeMismatch)
+        }
+        ret => panic!("unexpected result {:?}", ret),
+    }
+    assert_eq!(r.calls.get(), 1);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct MistypedImportFuncTest;
+
+impl FunctionResolver for MistypedImportFuncTest {
+    fn invoke(
+        &self,
+        _name: &str,
+        _field_name: &str,
+        _args: &[RuntimeValue],
+    ) -> Result<Vec<RuntimeValue>, RuntimeError> {
+        Ok(vec![RuntimeValue::I64(3)])
+    }
+}
+
+#[test]
+fn convert_references_without_panicking() {
+    let n: Result<u32, RuntimeError> = RuntimeValue::I32(-1).try_into();
+    assert_eq!(n.ok(), Some(u32::MAX));
+    let r: Result<u32, RuntimeError> = RuntimeValue::FuncRef(None).try_into();
+    assert!(matches!(r, Err(RuntimeError::TypeError)));
+    let r: Result<usize, RuntimeError> = RuntimeValue::ExternRef(None).try_into();
+    assert!(matches!(r, Err(RuntimeError::TypeError)));
+}
+
+#[test]
+fn reject_mistyped_values_at_host_boundary() -> Result<(), yaw::error::YawError> {
+    let mut file = fs::File::open("./fixtures/wasm/import_func.wasm")?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let r = MistypedImportFuncTest;
+    let mut imports = Imports::new();
+    imports.add_function(&r);
+    let ins = yaw::instantiate(&buf, Some(&imports))?;
+    let is_type_error = |ret: Result<Vec<RuntimeValue>, yaw::error::YawError>| {
+        matches!(
+            ret,
+            Err(yaw::YawError::RuntimeError {
+                error: RuntimeError::TypeError,
+                ..
+            })
+        )
+    };
+    assert!(is_type_error(
+        ins.invoke("exported_func", &[RuntimeValue::I32(0)])
+    ));
+    assert!(is_type_error(ins.invoke("imported_func", &[])));
+    assert!(is_type_error(
+        ins.invoke("imported_func", &[RuntimeValue::F32(0.0)])
+    ));
+    assert!(is_type_error(
+        ins.invoke("imported_func", &[RuntimeValue::I32(0)])
+    ));
+    assert!(is_type_error(ins.invoke("exported_func", &[])));
+    Ok(())
+}
+
 #[test]
 fn import_global() -> Result<(), yaw::error::YawError> {
     let mut file = fs::File::open("./fixtures/wasm/global_import.wasm")?;
@@ -290,7 +859,7 @@ fn import_global() -> Result<(), yaw::error::YawError> {
         ))),
     );
     let ins = yaw::instantiate(&buf, Some(&imports))?;
-    let ret = ins.invoke("global", &[RuntimeValue::I32(0)])?;
+    let ret = ins.invoke("global", &[RuntimeValue::I32(0), RuntimeValue::I32(0)])?;
     assert_eq!(vec![RuntimeValue::I32(42)], ret);
     Ok(())
 }
@@ -519,3 +1088,13 @@ fn testsuite_left_to_right() -> Result<(), yaw::error::YawError> {
 fn testsuite_load() -> Result<(), yaw::error::YawError> {
     exec_testsuite("./testsuite/load.wast")
 }
+
+#[test]
+fn testsuite_sign_extension_ops_i32() -> Result<(), yaw::error::YawError> {
+    exec_testsuite("./testsuite/proposals/sign-extension-ops/i32.wast")
+}
+
+#[test]
+fn testsuite_sign_extension_ops_i64() -> Result<(), yaw::error::YawError> {
+    exec_testsuite("./testsuite/proposals/sign-extension-ops/i64.wast")
+}
@@ -6,10 +6,11 @@ use std::path::Path;
 use std::rc::Rc;
 
 use wabt::script::{Action, Command, CommandKind, ScriptParser, Value};
+use wabt::Features;
 use yaw::types::*;
 use yaw::{
     ExternalKind, FunctionResolver, Global, ImportResolver, MemoryDescriptor, MemoryRef,
-    RuntimeError, TableInstance, TableRef, ValueType, VM,
+    RuntimeError, TableInstance, TableRef, TrapCode, ValueType, VM,
 };
 
 #[derive(Debug, Clone)]
@@ -204,14 +205,16 @@ pub fn exec_testsuite(p: impl AsRef<Path>) -> Result<(), yaw::error::YawError> {
     m.add_memory(
         "spectest",
         "memory",
-        MemoryRef::new(MemoryDescriptor::new(1, Some(2))),
+        MemoryRef::new(MemoryDescriptor::new(1, Some(2)))?,
     );
 
     let mut buf = vec![];
     let mut file = fs::File::open(p.as_ref())?;
     file.read_to_end(&mut buf)?;
-    let s = String::from_utf8(buf).unwrap();
-    let mut parser = ScriptParser::from_str(&s).unwrap();
+    let mut features = Features::new();
+    features.enable_sign_extension();
+    let mut parser =
+        ScriptParser::from_source_and_name_with_features(&buf, "test.wast", features).unwrap();
     while let Some(Command { kind, .. }) = parser.next().unwrap() {
         match kind {
             CommandKind::Module { module, name } => {
@@ -292,18 +295,30 @@ pub fn exec_testsuite(p: impl AsRef<Path>) -> Result<(), yaw::error::YawError> {
                         .collect();
                     let ret = ins.invoke(&field.to_string(), &args);
                     match ret {
-                        Err(err) => assert_eq!(message, err.to_string()),
+                        Err(yaw::YawError::Trap { trap }) => {
+                            assert_eq!(message, trap.code().to_string())
+                        }
+                        Err(err) => panic!("expected trap `{}`, got `{}`", message, err),
                         Ok(_) => panic!("error"),
                     }
                 }
                 // }
             }
-            CommandKind::AssertInvalid { .. } => {
-                // TODO: We need to add validation when loading.RuntimeValue
-                //       Then add invalid assertion
+            CommandKind::AssertInvalid { module, message } => {
+                let ret = yaw::instantiate(&module.into_vec(), Some(&m));
+                match ret {
+                    Err(yaw::YawError::ValidationError { .. }) => {}
+                    Err(err) => panic!("expected `{}`, got `{}`", message, err),
+                    Ok(_) => panic!("expected `{}`, but the module is valid", message),
+                }
             }
-            CommandKind::AssertMalformed { .. } => {
-                // TODO:
+            CommandKind::AssertMalformed { module, message } => {
+                let ret = yaw::instantiate(&module.into_vec(), Some(&m));
+                match ret {
+                    Err(yaw::YawError::DecodeError { .. }) => {}
+                    Err(err) => panic!("expected `{}`, got `{}`", message, err),
+                    Ok(_) => panic!("expected `{}`, but the module is decoded", message),
+                }
             }
             CommandKind::AssertReturnCanonicalNan { .. } => {
                 // TODO:
@@ -311,8 +326,35 @@ pub fn exec_testsuite(p: impl AsRef<Path>) -> Result<(), yaw::error::YawError> {
             CommandKind::AssertReturnArithmeticNan { .. } => {
                 // TODO:
             }
-            CommandKind::AssertExhaustion { .. } => {
-                // TODO:
+            CommandKind::AssertExhaustion { action, message } => {
+                if let Action::Invoke {
+                    field,
+                    args,
+                    module,
+                    ..
+                } = action
+                {
+                    let ins_ref = m.inner.borrow().modules[&module].clone();
+                    let ins = ins_ref.borrow_mut();
+                    let args: Vec<RuntimeValue> = args
+                        .into_iter()
+                        .map(|arg| match arg {
+                            Value::I32(v) => RuntimeValue::I32(v),
+                            Value::I64(v) => RuntimeValue::I64(v),
+                            Value::F32(v) => RuntimeValue::F32(v),
+                            Value::F64(v) => RuntimeValue::F64(v),
+                            Value::V128(v) => RuntimeValue::V128(v),
+                        })
+                        .collect();
+                    match ins.invoke(&field.to_string(), &args) {
+                        Err(yaw::YawError::Trap { trap }) => {
+                            assert_eq!(trap.code(), TrapCode::StackOverflow);
+                            assert_eq!(message, trap.code().to_string());
+                        }
+                        Err(err) => panic!("expected exhaustion `{}`, got `{}`", message, err),
+                        Ok(_) => panic!("error"),
+                    }
+                }
             }
             CommandKind::AssertUnlinkable { .. } => {
                 // TODO:
@@ -332,7 +374,11 @@ pub fn exec_testsuite(p: impl AsRef<Path>) -> Result<(), yaw::error::YawError> {
                 for (k, v) in exports.inner() {
                     match v.kind {
                         ExternalKind::Table => {
-                            m.add_table(as_name.clone(), k.clone(), mo.resolve_table());
+                            m.add_table(
+                                as_name.clone(),
+                                k.clone(),
+                                mo.resolve_table(v.index as usize)?,
+                            );
                         }
                         ExternalKind::Global => {
                             m.add_global(